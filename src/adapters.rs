@@ -0,0 +1,383 @@
+//! Iterator adapters used by the `for` clause modifiers and the `take`
+//! clauses of [`comp!`].
+//!
+//! These adapters work on any iterator, not only on slices, and buffer no
+//! more items than they need to produce their next output item.
+//!
+//! [`comp!`]: ../macro.comp.html
+
+use core::convert::TryInto;
+
+/// Create an iterator over overlapping windows of `N` consecutive items.
+///
+/// This is what `for pattern in window N of expression` expands to.
+///
+/// # Panics
+///
+/// Panics if `N` is zero.
+///
+/// # Examples
+///
+/// The pattern must match the size of the window:
+///
+/// ```compile_fail
+/// use py_comp::comp;
+///
+/// let _ = comp!(a + b; for [a, b] in window 3 of 0..5);
+/// ```
+pub fn windows<const N: usize, I: IntoIterator>(iter: I) -> Windows<I::IntoIter, N>
+where
+    I::Item: Clone,
+{
+    assert!(N != 0, "window size must not be zero");
+    Windows {
+        iter: iter.into_iter(),
+        window: None,
+    }
+}
+
+/// Create an iterator which maps the items of `iter` until one of the `take`
+/// clauses of its level is done.
+///
+/// This is what a level with `take` clauses expands to. The closure returns
+/// the mapped item along with whether a `take` clause is done, in which case
+/// no more items are pulled from `iter`. If `done` is already `true`, no item
+/// is pulled at all.
+pub fn until<I, F, B>(iter: I, done: bool, f: F) -> Until<I::IntoIter, F>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> (B, bool),
+{
+    Until {
+        iter: iter.into_iter(),
+        done,
+        f,
+    }
+}
+
+/// Create an iterator over pairs of consecutive items.
+///
+/// This is what `for pattern in pairwise expression` expands to.
+pub fn pairwise<I: IntoIterator>(iter: I) -> Pairwise<I::IntoIter>
+where
+    I::Item: Clone,
+{
+    Pairwise {
+        iter: iter.into_iter(),
+        previous: None,
+    }
+}
+
+/// An iterator over overlapping windows of `N` consecutive items.
+///
+/// Each window is yielded as an array, so the pattern it is matched against
+/// is checked against the size of the window at compile time.
+/// If the underlying iterator yields fewer than `N` items, no windows are
+/// yielded.
+///
+/// This `struct` is created by the [`windows`] function.
+#[derive(Debug, Clone)]
+pub struct Windows<I: Iterator, const N: usize> {
+    iter: I,
+    window: Option<[I::Item; N]>,
+}
+
+impl<I, const N: usize> Iterator for Windows<I, N>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = [I::Item; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.window {
+            Some(window) => {
+                let item = self.iter.next()?;
+                window.rotate_left(1);
+                window[N - 1] = item;
+                Some(window.clone())
+            }
+            None => {
+                let items: Vec<I::Item> = self.iter.by_ref().take(N).collect();
+                let window: [I::Item; N] = items.try_into().ok()?;
+                self.window = Some(window.clone());
+                Some(window)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        match self.window {
+            Some(_) => (lower, upper),
+            None => (
+                lower.saturating_sub(N - 1),
+                upper.map(|upper| upper.saturating_sub(N - 1)),
+            ),
+        }
+    }
+}
+
+/// An iterator which maps items until one of the `take` clauses of its level
+/// is done.
+///
+/// Unlike [`Iterator::map_while`], this stops right after the last item is
+/// taken, instead of pulling another item from the underlying iterator to
+/// find out that it is not wanted.
+///
+/// This `struct` is created by the [`until`] function.
+#[derive(Debug, Clone)]
+pub struct Until<I, F> {
+    iter: I,
+    done: bool,
+    f: F,
+}
+
+impl<I, F, B> Iterator for Until<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> (B, bool),
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        if self.done {
+            return None;
+        }
+        let (item, done) = (self.f)(self.iter.next()?);
+        self.done = done;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, self.iter.size_hint().1)
+        }
+    }
+}
+
+/// An iterator over pairs of consecutive items.
+///
+/// If the underlying iterator yields fewer than two items, no pairs are
+/// yielded.
+///
+/// This `struct` is created by the [`pairwise`] function.
+#[derive(Debug, Clone)]
+pub struct Pairwise<I: Iterator> {
+    iter: I,
+    previous: Option<I::Item>,
+}
+
+impl<I> Iterator for Pairwise<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let previous = match self.previous.take() {
+            Some(previous) => previous,
+            None => self.iter.next()?,
+        };
+        let current = self.iter.next()?;
+        self.previous = Some(current.clone());
+        Some((previous, current))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        match self.previous {
+            Some(_) => (lower, upper),
+            None => (
+                lower.saturating_sub(1),
+                upper.map(|upper| upper.saturating_sub(1)),
+            ),
+        }
+    }
+}
+
+/// Create an iterator over non-overlapping chunks of `size` items, each
+/// collected into a `Vec`.
+///
+/// This is what `for pattern in chunks size [keep | drop | pad value] of
+/// expression` expands to. The `remainder` decides what happens to a final
+/// chunk shorter than `size`.
+///
+/// # Panics
+///
+/// Panics if `size` is zero.
+pub fn chunks<I, R>(iter: I, size: usize, remainder: R) -> Chunks<I::IntoIter, R>
+where
+    I: IntoIterator,
+    R: Remainder<I::Item>,
+{
+    assert!(size != 0, "chunk size must not be zero");
+    Chunks {
+        iter: iter.into_iter(),
+        size,
+        remainder,
+    }
+}
+
+/// Create an iterator over non-overlapping chunks of `N` items, each
+/// collected into an array.
+///
+/// This is what `for pattern in chunks [N] (drop | pad value) of expression`
+/// expands to. Since an array can not hold a shorter final chunk, the
+/// `remainder` must either drop or pad it.
+///
+/// # Panics
+///
+/// Panics if `N` is zero.
+///
+/// # Examples
+///
+/// A partial final chunk can not be kept:
+///
+/// ```compile_fail
+/// use py_comp::comp;
+///
+/// let _ = comp!(a + b; for [a, b] in chunks [2] keep of 0..5);
+/// ```
+pub fn array_chunks<const N: usize, I, R>(iter: I, remainder: R) -> ArrayChunks<I::IntoIter, R, N>
+where
+    I: IntoIterator,
+    R: ExactRemainder<I::Item>,
+{
+    assert!(N != 0, "chunk size must not be zero");
+    ArrayChunks {
+        iter: iter.into_iter(),
+        remainder,
+    }
+}
+
+/// A policy for the final chunk of a chunking iterator, when it is shorter
+/// than the requested chunk size.
+pub trait Remainder<T> {
+    /// Turn the final, partial `chunk` into the last item of the iterator,
+    /// or return `None` to leave it out.
+    fn finish(&mut self, chunk: Vec<T>, size: usize) -> Option<Vec<T>>;
+}
+
+/// A [`Remainder`] policy which always produces full chunks.
+pub trait ExactRemainder<T>: Remainder<T> {}
+
+/// Yield the final chunk even if it is shorter than the others.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keep;
+
+/// Leave out the final chunk if it is shorter than the others.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Discard;
+
+/// Fill up the final chunk with clones of the given value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pad<T>(pub T);
+
+impl<T> Remainder<T> for Keep {
+    fn finish(&mut self, chunk: Vec<T>, _size: usize) -> Option<Vec<T>> {
+        Some(chunk)
+    }
+}
+
+impl<T> Remainder<T> for Discard {
+    fn finish(&mut self, _chunk: Vec<T>, _size: usize) -> Option<Vec<T>> {
+        None
+    }
+}
+
+impl<T: Clone> Remainder<T> for Pad<T> {
+    fn finish(&mut self, mut chunk: Vec<T>, size: usize) -> Option<Vec<T>> {
+        chunk.resize(size, self.0.clone());
+        Some(chunk)
+    }
+}
+
+impl<T> ExactRemainder<T> for Discard {}
+
+impl<T: Clone> ExactRemainder<T> for Pad<T> {}
+
+/// Collect the next chunk of up to `size` items, passing a partial chunk to
+/// the `remainder` policy.
+fn next_chunk<I, R>(iter: &mut I, size: usize, remainder: &mut R) -> Option<Vec<I::Item>>
+where
+    I: Iterator,
+    R: Remainder<I::Item>,
+{
+    let mut chunk = Vec::with_capacity(size);
+    chunk.extend(iter.take(size));
+    if chunk.len() == size {
+        Some(chunk)
+    } else if chunk.is_empty() {
+        None
+    } else {
+        remainder.finish(chunk, size)
+    }
+}
+
+/// Bound the number of chunks of `size` items in an iterator with the given
+/// `size_hint`, regardless of the remainder policy.
+fn chunks_size_hint((lower, upper): (usize, Option<usize>), size: usize) -> (usize, Option<usize>) {
+    (
+        lower / size,
+        upper.map(|upper| upper / size + (upper % size != 0) as usize),
+    )
+}
+
+/// An iterator over non-overlapping chunks of items, each collected into
+/// a `Vec`.
+///
+/// This `struct` is created by the [`chunks`] function.
+#[derive(Debug, Clone)]
+pub struct Chunks<I, R> {
+    iter: I,
+    size: usize,
+    remainder: R,
+}
+
+impl<I, R> Iterator for Chunks<I, R>
+where
+    I: Iterator,
+    R: Remainder<I::Item>,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_chunk(&mut self.iter, self.size, &mut self.remainder)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        chunks_size_hint(self.iter.size_hint(), self.size)
+    }
+}
+
+/// An iterator over non-overlapping chunks of `N` items, each collected into
+/// an array.
+///
+/// This `struct` is created by the [`array_chunks`] function.
+#[derive(Debug, Clone)]
+pub struct ArrayChunks<I, R, const N: usize> {
+    iter: I,
+    remainder: R,
+}
+
+impl<I, R, const N: usize> Iterator for ArrayChunks<I, R, N>
+where
+    I: Iterator,
+    R: ExactRemainder<I::Item>,
+{
+    type Item = [I::Item; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_chunk(&mut self.iter, N, &mut self.remainder)?
+            .try_into()
+            .ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        chunks_size_hint(self.iter.size_hint(), N)
+    }
+}
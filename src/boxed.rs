@@ -0,0 +1,34 @@
+//! The boxed iterators which [`comp_boxed!`] and [`comp_dyn!`] evaluate to.
+//!
+//! Comprehensions built by different expressions have different types, even
+//! when they yield the same items. Boxing them erases their types, so they
+//! can be returned from different branches of a function, or stored without
+//! naming them.
+//!
+//! [`comp_boxed!`]: ../macro.comp_boxed.html
+//! [`comp_dyn!`]: ../macro.comp_dyn.html
+
+/// A boxed comprehension yielding items of type `T`, which borrows for `'a`.
+pub type Boxed<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// A boxed comprehension yielding items of type `T`, which borrows for `'a`
+/// and can be sent to another thread.
+pub type BoxedSend<'a, T> = Box<dyn Iterator<Item = T> + Send + 'a>;
+
+/// Box an iterator as a [`Boxed`] comprehension.
+#[inline]
+pub fn boxed<'a, I>(iter: I) -> Boxed<'a, I::Item>
+where
+    I: Iterator + 'a,
+{
+    Box::new(iter)
+}
+
+/// Box an iterator as a [`BoxedSend`] comprehension.
+#[inline]
+pub fn boxed_send<'a, I>(iter: I) -> BoxedSend<'a, I::Item>
+where
+    I: Iterator + Send + 'a,
+{
+    Box::new(iter)
+}
@@ -0,0 +1,279 @@
+//! The resumable iterator which [`comp_resumable!`] expands to, and the
+//! [`Cursor`] recording its position.
+//!
+//! [`comp_resumable!`]: ../macro.comp_resumable.html
+
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+/// The position of a [`Resumable`] comprehension.
+///
+/// This holds one index for every `for` clause, counting the items taken from
+/// the current source of that clause, including the ones rejected by the
+/// following `if` clauses. It is formatted and parsed as the indices separated
+/// by commas, such as `3,1,4`, so it can be stored as a checkpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    indices: Vec<usize>,
+}
+
+impl Cursor {
+    /// Create a cursor from the indices of every `for` clause.
+    pub fn new(indices: Vec<usize>) -> Self {
+        Cursor { indices }
+    }
+
+    /// The indices of every `for` clause.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Convert the cursor into the indices of every `for` clause.
+    pub fn into_indices(self) -> Vec<usize> {
+        self.indices
+    }
+}
+
+impl From<Vec<usize>> for Cursor {
+    fn from(indices: Vec<usize>) -> Self {
+        Cursor::new(indices)
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, index) in self.indices.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned when parsing a [`Cursor`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCursorError(ParseIntError);
+
+impl fmt::Display for ParseCursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cursor index: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCursorError {}
+
+impl FromStr for Cursor {
+    type Err = ParseCursorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|index| index.trim().parse().map_err(ParseCursorError))
+            .collect::<Result<_, _>>()
+            .map(Cursor::new)
+    }
+}
+
+/// A level of a [`Resumable`] comprehension, made of a `for` clause and the
+/// clauses following it.
+pub trait Level {
+    /// The type of the items of the comprehension.
+    type Item;
+
+    /// The number of `for` clauses in this level and the nested ones.
+    const DEPTH: usize;
+
+    /// Advance to the next item of the comprehension.
+    fn next(&mut self) -> Option<Self::Item>;
+
+    /// Append the indices of this level and the nested ones to `indices`.
+    fn indices(&self, indices: &mut Vec<usize>);
+
+    /// Move a fresh level to the position given by `indices`.
+    fn seek(&mut self, indices: &[usize]);
+}
+
+/// The level of the final `for` clause.
+#[derive(Debug, Clone)]
+pub struct Last<I, F> {
+    iter: I,
+    f: F,
+    count: usize,
+}
+
+/// Create the level of the final `for` clause, with a closure evaluating the
+/// following clauses and the item expression.
+pub fn last<I, F, T>(iter: I, f: F) -> Last<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Option<T>,
+{
+    Last { iter, f, count: 0 }
+}
+
+impl<I, F, T> Level for Last<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Option<T>,
+{
+    type Item = T;
+
+    const DEPTH: usize = 1;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let item = self.iter.next()?;
+            self.count += 1;
+            if let Some(item) = (self.f)(item) {
+                return Some(item);
+            }
+        }
+    }
+
+    fn indices(&self, indices: &mut Vec<usize>) {
+        indices.push(self.count);
+    }
+
+    fn seek(&mut self, indices: &[usize]) {
+        self.count = indices[0];
+        if self.count > 0 {
+            self.iter.nth(self.count - 1);
+        }
+    }
+}
+
+/// The level of a `for` clause followed by another one.
+#[derive(Debug, Clone)]
+pub struct Nested<I, F, L> {
+    iter: I,
+    f: F,
+    count: usize,
+    inner: Option<L>,
+}
+
+/// Create the level of a `for` clause followed by another one, with a closure
+/// evaluating the following clauses and creating the nested level.
+pub fn nested<I, F, L>(iter: I, f: F) -> Nested<I, F, L>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Option<L>,
+    L: Level,
+{
+    Nested {
+        iter,
+        f,
+        count: 0,
+        inner: None,
+    }
+}
+
+impl<I, F, L> Level for Nested<I, F, L>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> Option<L>,
+    L: Level,
+{
+    type Item = L::Item;
+
+    const DEPTH: usize = L::DEPTH + 1;
+
+    fn next(&mut self) -> Option<L::Item> {
+        loop {
+            if let Some(inner) = &mut self.inner {
+                if let Some(item) = inner.next() {
+                    return Some(item);
+                }
+                self.inner = None;
+            }
+            let item = self.iter.next()?;
+            self.count += 1;
+            self.inner = (self.f)(item);
+        }
+    }
+
+    fn indices(&self, indices: &mut Vec<usize>) {
+        indices.push(self.count);
+        match &self.inner {
+            Some(inner) => inner.indices(indices),
+            None => indices.extend((0..L::DEPTH).map(|_| 0)),
+        }
+    }
+
+    // The item of this level which the nested level was created for is taken
+    // again, so the nested level is created again and moved in turn. Between
+    // two items, a nested level which exists has always taken an item, so a
+    // zero index means that there is none, such as once this level is done.
+    fn seek(&mut self, indices: &[usize]) {
+        if indices[0] == 0 {
+            return;
+        }
+        if indices[1] == 0 {
+            self.count = indices[0];
+            self.iter.nth(self.count - 1);
+            return;
+        }
+        if let Some(item) = self.iter.nth(indices[0] - 1) {
+            self.count = indices[0];
+            self.inner = (self.f)(item);
+            if let Some(inner) = &mut self.inner {
+                inner.seek(&indices[1..]);
+            }
+        }
+    }
+}
+
+/// A comprehension which can report its position as a [`Cursor`], and be
+/// rebuilt at that position.
+///
+/// This is what [`comp_resumable!`] expands to.
+///
+/// [`comp_resumable!`]: ../macro.comp_resumable.html
+#[derive(Debug, Clone)]
+pub struct Resumable<L> {
+    level: L,
+}
+
+impl<L: Level> Resumable<L> {
+    /// Wrap the level of the first `for` clause of a comprehension.
+    pub fn new(level: L) -> Self {
+        Resumable { level }
+    }
+
+    /// The position of the comprehension after the items yielded so far.
+    pub fn cursor(&self) -> Cursor {
+        let mut indices = Vec::with_capacity(L::DEPTH);
+        self.level.indices(&mut indices);
+        Cursor::new(indices)
+    }
+
+    /// Move a comprehension which has not yielded any item yet to the
+    /// position given by `cursor`, so that it yields the items following
+    /// that position.
+    ///
+    /// Only the items of the enclosing `for` clauses at that position are
+    /// evaluated again. Skipping the items before them uses `nth`, which is
+    /// constant-time for sources such as slices and ranges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor does not have an index for every `for` clause.
+    pub fn resume(mut self, cursor: &Cursor) -> Self {
+        assert_eq!(
+            cursor.indices().len(),
+            L::DEPTH,
+            "cursor must have an index for every `for` clause"
+        );
+        self.level.seek(cursor.indices());
+        self
+    }
+}
+
+impl<L: Level> Iterator for Resumable<L> {
+    type Item = L::Item;
+
+    fn next(&mut self) -> Option<L::Item> {
+        self.level.next()
+    }
+}
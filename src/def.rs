@@ -0,0 +1,50 @@
+//! The re-iterable comprehension which [`comp_def!`] expands to.
+//!
+//! [`comp_def!`]: ../macro.comp_def.html
+
+use core::fmt;
+
+/// A comprehension which can be iterated over any number of times.
+///
+/// This holds a function building the iterator of the comprehension from the
+/// start, which is called every time a reference to the `Def` is iterated
+/// over, or [`iter`](Self::iter) is called.
+#[derive(Clone, Copy)]
+pub struct Def<F> {
+    f: F,
+}
+
+impl<F, I> Def<F>
+where
+    F: Fn() -> I,
+    I: Iterator,
+{
+    /// Create a comprehension from a function building its iterator.
+    pub fn new(f: F) -> Self {
+        Def { f }
+    }
+
+    /// Build a new iterator over the items of the comprehension.
+    pub fn iter(&self) -> I {
+        (self.f)()
+    }
+}
+
+impl<F, I> IntoIterator for &Def<F>
+where
+    F: Fn() -> I,
+    I: Iterator,
+{
+    type Item = I::Item;
+    type IntoIter = I;
+
+    fn into_iter(self) -> I {
+        self.iter()
+    }
+}
+
+impl<F> fmt::Debug for Def<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Def").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,126 @@
+//! The plan which [`comp_explain!`] describes a comprehension with.
+//!
+//! [`comp_explain!`]: ../macro.comp_explain.html
+
+use std::fmt;
+
+/// The kind of a clause following a `for` clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClauseKind {
+    /// An `if` clause.
+    If,
+    /// An `if let` clause.
+    IfLet,
+    /// A `take` clause.
+    Take,
+    /// A `skip` clause.
+    Skip,
+    /// A `step` clause.
+    Step,
+    /// A `scan` clause.
+    Scan,
+}
+
+/// A clause following a `for` clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    /// The kind of the clause.
+    pub kind: ClauseKind,
+    /// The source text of the clause.
+    pub source: &'static str,
+}
+
+/// A level of nesting, made of a `for` clause and the clauses following it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+    /// The source text of the `for` clause, with any modifier replaced by the
+    /// adapter it expands to.
+    pub source: &'static str,
+    /// The clauses following the `for` clause, up to the next one.
+    pub clauses: Vec<Clause>,
+    /// The comment of the `@emit` rule of [`comp!`] in `src/lib.rs` which
+    /// expands this level.
+    ///
+    /// [`comp!`]: ../macro.comp.html
+    pub rule: &'static str,
+    /// The iterator adapters applied to the source of the level.
+    pub pipeline: &'static str,
+    /// The closure passed to the adapters, and what it returns.
+    pub closure: &'static str,
+}
+
+/// The plan of a comprehension, listing its levels of nesting from the
+/// outermost to the innermost.
+///
+/// This is formatted as an indented tree, and may be converted to a Graphviz
+/// graph with [`to_dot`](Self::to_dot).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// The source text of the item expression.
+    pub item: &'static str,
+    /// The levels of nesting, from the outermost to the innermost.
+    pub levels: Vec<Level>,
+}
+
+impl Plan {
+    /// Describe the plan as a Graphviz graph in the DOT language, with a node
+    /// for the item expression and every clause.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph comp {\n    node [shape=box];\n");
+        let mut previous = None;
+        for (i, level) in self.levels.iter().enumerate() {
+            let node = format!("level{}", i);
+            dot.push_str(&format!(
+                "    {} [label=\"{}\\l{}\\l{}\\l\"];\n",
+                node,
+                escape(level.source),
+                escape(level.pipeline),
+                escape(level.closure),
+            ));
+            link(&mut dot, &mut previous, node);
+            for (j, clause) in level.clauses.iter().enumerate() {
+                let node = format!("level{}_clause{}", i, j);
+                dot.push_str(&format!(
+                    "    {} [label=\"{}\", shape=diamond];\n",
+                    node,
+                    escape(clause.source)
+                ));
+                link(&mut dot, &mut previous, node);
+            }
+        }
+        dot.push_str(&format!(
+            "    item [label=\"{}\", shape=ellipse];\n",
+            escape(self.item)
+        ));
+        link(&mut dot, &mut previous, String::from("item"));
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn link(dot: &mut String, previous: &mut Option<String>, node: String) {
+    if let Some(previous) = previous {
+        dot.push_str(&format!("    {} -> {};\n", previous, node));
+    }
+    *previous = Some(node);
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (depth, level) in self.levels.iter().enumerate() {
+            let indent = "    ".repeat(depth);
+            writeln!(f, "{}{}", indent, level.source)?;
+            writeln!(f, "{}    rule: {}", indent, level.rule)?;
+            writeln!(f, "{}    pipeline: {}", indent, level.pipeline)?;
+            writeln!(f, "{}    closure: {}", indent, level.closure)?;
+            for clause in &level.clauses {
+                writeln!(f, "{}    {}", indent, clause.source)?;
+            }
+        }
+        writeln!(f, "{}yield {}", "    ".repeat(self.levels.len()), self.item)
+    }
+}
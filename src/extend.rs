@@ -0,0 +1,176 @@
+//! Helpers for the macros which collect a comprehension into existing or
+//! multiple collections, such as [`comp_extend!`] and [`comp_unzip!`].
+//!
+//! [`comp_extend!`]: ../macro.comp_extend.html
+//! [`comp_unzip!`]: ../macro.comp_unzip.html
+
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+use std::hash::{BuildHasher, Hash};
+
+/// A collection which can reserve capacity for additional items ahead of
+/// being extended with them.
+///
+/// [`comp_extend!`] reserves the lower bound of the comprehension's
+/// `size_hint` through this trait before extending the target. It is
+/// implemented for the collections of the standard library, doing nothing for
+/// the ones which have no notion of capacity, and may be implemented for other
+/// collections to use them as targets of [`comp_extend!`].
+///
+/// [`comp_extend!`]: ../macro.comp_extend.html
+pub trait Reserve {
+    /// Reserve capacity for at least `additional` more items.
+    fn reserve(&mut self, additional: usize);
+}
+
+impl<T> Reserve for Vec<T> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl<T> Reserve for VecDeque<T> {
+    fn reserve(&mut self, additional: usize) {
+        VecDeque::reserve(self, additional);
+    }
+}
+
+impl<T: Ord> Reserve for BinaryHeap<T> {
+    fn reserve(&mut self, additional: usize) {
+        BinaryHeap::reserve(self, additional);
+    }
+}
+
+impl Reserve for String {
+    fn reserve(&mut self, additional: usize) {
+        String::reserve(self, additional);
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Reserve for HashMap<K, V, S> {
+    fn reserve(&mut self, additional: usize) {
+        HashMap::reserve(self, additional);
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Reserve for HashSet<T, S> {
+    fn reserve(&mut self, additional: usize) {
+        HashSet::reserve(self, additional);
+    }
+}
+
+impl<K, V> Reserve for BTreeMap<K, V> {
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+impl<T> Reserve for BTreeSet<T> {
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+impl<T> Reserve for LinkedList<T> {
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+/// Extend a tuple of collections with a tuple of items, one item per
+/// collection.
+///
+/// This is implemented for tuples of up to 12 collections.
+pub trait ExtendTuple<T> {
+    /// Extend each collection in `self` with the item in the same position
+    /// in `items`.
+    fn extend_tuple(&mut self, items: T);
+}
+
+macro_rules! impl_extend_tuple {
+    ($(($collection: ident, $item: ident, $index: tt)),+) => {
+        impl<$($collection: Extend<$item>, $item),+> ExtendTuple<($($item,)+)>
+            for ($($collection,)+)
+        {
+            fn extend_tuple(&mut self, items: ($($item,)+)) {
+                $( self.$index.extend(core::iter::once(items.$index)); )+
+            }
+        }
+    };
+}
+
+impl_extend_tuple!((A, TA, 0));
+impl_extend_tuple!((A, TA, 0), (B, TB, 1));
+impl_extend_tuple!((A, TA, 0), (B, TB, 1), (C, TC, 2));
+impl_extend_tuple!((A, TA, 0), (B, TB, 1), (C, TC, 2), (D, TD, 3));
+impl_extend_tuple!((A, TA, 0), (B, TB, 1), (C, TC, 2), (D, TD, 3), (E, TE, 4));
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6),
+    (H, TH, 7)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6),
+    (H, TH, 7),
+    (I, TI, 8)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6),
+    (H, TH, 7),
+    (I, TI, 8),
+    (J, TJ, 9)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6),
+    (H, TH, 7),
+    (I, TI, 8),
+    (J, TJ, 9),
+    (K, TK, 10)
+);
+impl_extend_tuple!(
+    (A, TA, 0),
+    (B, TB, 1),
+    (C, TC, 2),
+    (D, TD, 3),
+    (E, TE, 4),
+    (F, TF, 5),
+    (G, TG, 6),
+    (H, TH, 7),
+    (I, TI, 8),
+    (J, TJ, 9),
+    (K, TK, 10),
+    (L, TL, 11)
+);
@@ -0,0 +1,376 @@
+//! The random-access product which [`comp_indexed!`] expands to.
+//!
+//! [`comp_indexed!`]: ../macro.comp_indexed.html
+
+use core::iter::FusedIterator;
+use core::ops::{Range, RangeInclusive};
+
+/// A source of a [`comp_indexed!`] `for` clause, whose items can be computed
+/// from their position alone.
+///
+/// This is implemented for references to slices, arrays and vectors, which
+/// yield references to their items, and for ranges of integers.
+///
+/// [`comp_indexed!`]: ../macro.comp_indexed.html
+pub trait RandomAccess {
+    /// The type of the items of the source.
+    type Item;
+
+    /// The number of items in the source.
+    fn len(&self) -> usize;
+
+    /// Whether the source has no items.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The item at `index`, which must be less than [`len`](Self::len).
+    fn get(&self, index: usize) -> Self::Item;
+}
+
+impl<'a, T> RandomAccess for &'a [T] {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+impl<'a, T, const N: usize> RandomAccess for &'a [T; N] {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn get(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+impl<'a, T> RandomAccess for &'a Vec<T> {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn get(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+// The items are computed with wrapping arithmetic, since the index of an
+// item of a signed range may not fit in its type, while the item does.
+
+macro_rules! impl_random_access_range {
+    ($($int: ty),+) => {$(
+        impl RandomAccess for Range<$int> {
+            type Item = $int;
+
+            fn len(&self) -> usize {
+                ExactSizeIterator::len(&self.clone())
+            }
+
+            fn get(&self, index: usize) -> $int {
+                self.start.wrapping_add(index as $int)
+            }
+        }
+    )+};
+}
+
+impl_random_access_range!(u8, u16, u32, usize, i8, i16, i32, isize);
+
+macro_rules! impl_random_access_range_inclusive {
+    ($($int: ty),+) => {$(
+        impl RandomAccess for RangeInclusive<$int> {
+            type Item = $int;
+
+            fn len(&self) -> usize {
+                ExactSizeIterator::len(&self.clone())
+            }
+
+            fn get(&self, index: usize) -> $int {
+                self.start().wrapping_add(index as $int)
+            }
+        }
+    )+};
+}
+
+// `RangeInclusive` of wider types is not an `ExactSizeIterator`, since its
+// length may not fit in a `usize`.
+impl_random_access_range_inclusive!(u8, u16, i8, i16);
+
+/// A tuple of [`RandomAccess`] sources, one for each `for` clause of a
+/// [`comp_indexed!`] invocation.
+///
+/// The items of the product are ordered like those of nested `for` loops
+/// over the sources, so the last source varies the fastest. This is
+/// implemented for tuples of up to 12 sources.
+///
+/// [`comp_indexed!`]: ../macro.comp_indexed.html
+pub trait Sources {
+    /// A tuple of one item of every source.
+    type Items;
+
+    /// The number of items in the product of the sources.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number does not fit in a `usize`.
+    fn len(&self) -> usize;
+
+    /// Whether the product of the sources has no items.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The items of the sources at the position `index` of the product,
+    /// which must be less than [`len`](Self::len).
+    fn get(&self, index: usize) -> Self::Items;
+}
+
+/// Split an index of a product of sources with the lengths `lens` into the
+/// indices of every source.
+fn positions<const N: usize>(lens: [usize; N], mut index: usize) -> [usize; N] {
+    let mut positions = [0; N];
+    for (position, len) in positions.iter_mut().zip(lens).rev() {
+        *position = index % len;
+        index /= len;
+    }
+    positions
+}
+
+macro_rules! impl_sources {
+    ($(($source: ident, $index: tt)),+) => {
+        impl<$($source: RandomAccess),+> Sources for ($($source,)+) {
+            type Items = ($($source::Item,)+);
+
+            fn len(&self) -> usize {
+                let len = Some(1usize);
+                $( let len = len.and_then(|len| len.checked_mul(self.$index.len())); )+
+                len.expect("comprehension has too many items to index")
+            }
+
+            fn get(&self, index: usize) -> Self::Items {
+                let positions = positions([$(self.$index.len()),+], index);
+                ($(self.$index.get(positions[$index]),)+)
+            }
+        }
+    };
+}
+
+impl_sources!((A, 0));
+impl_sources!((A, 0), (B, 1));
+impl_sources!((A, 0), (B, 1), (C, 2));
+impl_sources!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_sources!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_sources!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_sources!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_sources!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7)
+);
+impl_sources!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7),
+    (I, 8)
+);
+impl_sources!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7),
+    (I, 8),
+    (J, 9)
+);
+impl_sources!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7),
+    (I, 8),
+    (J, 9),
+    (K, 10)
+);
+impl_sources!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7),
+    (I, 8),
+    (J, 9),
+    (K, 10),
+    (L, 11)
+);
+
+/// A random-access iterator over the cartesian product of some
+/// [`Sources`], mapped by a closure.
+///
+/// This is what [`comp_indexed!`] expands to. Any item can be computed from
+/// its position in constant time, so besides being an [`ExactSizeIterator`]
+/// and a [`DoubleEndedIterator`] with constant-time `nth` and `nth_back`, it
+/// can be accessed like a slice with [`get`](Self::get) and split into
+/// independent halves with [`split_at`](Self::split_at).
+///
+/// [`comp_indexed!`]: ../macro.comp_indexed.html
+#[derive(Debug, Clone)]
+pub struct Indexed<S, F> {
+    sources: S,
+    f: F,
+    start: usize,
+    end: usize,
+}
+
+impl<S, F, T> Indexed<S, F>
+where
+    S: Sources,
+    F: Fn(S::Items) -> T,
+{
+    /// Create an iterator over the product of `sources`, mapped by `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of items in the product does not fit in a
+    /// `usize`.
+    pub fn new(sources: S, f: F) -> Self {
+        let end = sources.len();
+        Indexed {
+            sources,
+            f,
+            start: 0,
+            end,
+        }
+    }
+
+    /// The number of remaining items.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether there are no remaining items.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The remaining item at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index < self.len() {
+            Some((self.f)(self.sources.get(self.start + index)))
+        } else {
+            None
+        }
+    }
+
+    /// Split the remaining items into the first `mid` items and the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    pub fn split_at(self, mid: usize) -> (Self, Self)
+    where
+        S: Clone,
+        F: Clone,
+    {
+        assert!(mid <= self.len(), "split index is out of bounds");
+        let mid = self.start + mid;
+        let front = Indexed {
+            sources: self.sources.clone(),
+            f: self.f.clone(),
+            start: self.start,
+            end: mid,
+        };
+        let back = Indexed { start: mid, ..self };
+        (front, back)
+    }
+}
+
+impl<S, F, T> Iterator for Indexed<S, F>
+where
+    S: Sources,
+    F: Fn(S::Items) -> T,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let item = self.get(0)?;
+        self.start += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+
+    fn count(self) -> usize {
+        self.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<T> {
+        self.start += n.min(self.len());
+        self.next()
+    }
+
+    fn last(mut self) -> Option<T> {
+        self.next_back()
+    }
+}
+
+impl<S, F, T> DoubleEndedIterator for Indexed<S, F>
+where
+    S: Sources,
+    F: Fn(S::Items) -> T,
+{
+    fn next_back(&mut self) -> Option<T> {
+        let item = self.get(self.len().checked_sub(1)?)?;
+        self.end -= 1;
+        Some(item)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<T> {
+        self.end -= n.min(self.len());
+        self.next_back()
+    }
+}
+
+impl<S, F, T> ExactSizeIterator for Indexed<S, F>
+where
+    S: Sources,
+    F: Fn(S::Items) -> T,
+{
+}
+
+impl<S, F, T> FusedIterator for Indexed<S, F>
+where
+    S: Sources,
+    F: Fn(S::Items) -> T,
+{
+}
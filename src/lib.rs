@@ -8,13 +8,46 @@
 //! * The pattern between the `for` and `in` tokens is a fully-fledged
 //!   Rust pattern, which can be as simple as a simple token and as complex
 //!   as struct destructuring.
+//! * The pattern may be refutable, such as `Some(x)`, `(Some(a), b)`,
+//!   `Event::Quit` or `1..=9`, and the items it does not match are skipped.
+//!   Writing `for!` instead of `for` panics on them instead. A pattern made only
+//!   of names, `_`, references and tuples is bound directly by the closure of
+//!   its level, so a bare name other than `None` must be a new binding rather
+//!   than a constant.
 //! * The expression defining the iterator after the `in` token
 //!   must evaluate to either an `Iterator` or an `impl IntoIterator`.
+//! * The expression after the `in` token may be preceded by `window N of` to
+//!   iterate over arrays of `N` consecutive items, or by `pairwise` to iterate
+//!   over tuples of two consecutive items. These work on any iterator, buffer
+//!   only the current window, and require the items to be `Clone`.
+//! * The expression after the `in` token may also be preceded by
+//!   `chunks N of` to iterate over `Vec`s of `N` consecutive items. The final
+//!   chunk may be shorter than `N`, unless `chunks N drop of` is used to leave it
+//!   out or `chunks N pad value of` is used to fill it up with clones of `value`.
+//!   Writing `chunks [N]` instead of `chunks N` yields arrays of `N` items, in
+//!   which case either `drop` or `pad` must be used.
+//! * The expression after the `in` token of a nested `for` clause may instead be
+//!   marked `fixed`, as in `for b in fixed ys`, to promise that it does not
+//!   depend on the items of the enclosing levels. It is then evaluated once more
+//!   when the comprehension is created, to compute its `size_hint`.
 //! * The conditional expression after the `if` token must evaluate to
 //!   a boolean.
 //! * You may use an `if let` clause instead of the usual `if` clause wherever
 //!   `if` clauses are allowed. Any names introduced in the `if let` clause
 //!   are available in any following clause.
+//! * You may use `take`, `skip` and `step` clauses wherever `if` clauses are
+//!   allowed. They count the items reaching them at the current level of
+//!   nesting, after any earlier clauses of that level, and keep a separate count
+//!   for every item of the enclosing levels. `step n` lets through every `n`th
+//!   item, starting with the first one. Once a `take` clause has let through
+//!   its items, no more items are pulled from the source of its level.
+//! * You may use a `scan acc = init => update` clause wherever `if` clauses are
+//!   allowed, to keep a running accumulator like Python's `itertools.accumulate`.
+//!   `acc` starts as `init` for every item of the enclosing levels, and is
+//!   replaced by `update` for every item reaching the clause. `update` may use
+//!   the previous value of `acc`, and any following clause sees its new value.
+//!   The accumulator must be `Clone`, since a copy of it is kept for the next
+//!   item.
 //! * The expression in the beginning of the generator expression,
 //!   the expression following the `in` token, and the expression following
 //!   the `if` token, must all end with a semicolon (;). The only exception
@@ -24,6 +57,19 @@
 //! The expression replaced by the `comp!()` macro invocation is a lazy
 //! iterator whose lifetime is bound by any references it needs to capture.
 //! This means that it can be `.collect()`ed into any container you like.
+//! When a comprehension has no clauses other than `for` clauses, its
+//! `size_hint` never builds nested sources ahead of time. Unless the nested
+//! sources are known not to depend on the items of the enclosing levels, it
+//! only counts the items left in the nested sources being iterated. A nested
+//! source is known not to depend on them when it is marked `fixed` or written
+//! with literals only, such as `0..4` or `&[1, 2, 3]`. If all of them are, their
+//! length is computed once, when the comprehension is created, and the
+//! comprehension reports an exact `size_hint` whenever all of its sources do and
+//! the number of its items fits in a `usize`. It is not an `ExactSizeIterator`
+//! though, since like `Flatten` it may have more items than that.
+//! The iterator is `Clone` whenever its sources and the values its expressions
+//! capture are, and a clone resumes from the same position as the original,
+//! independently of it.
 //!
 //! Note though that, at least for now, all objects named in an `in` clause,
 //! (except for the first `in` clause) must be either `Copy` or introduced by
@@ -36,17 +82,27 @@
 //! `for` or `if let` clauses. This is because they may be used in multiple
 //! output items.
 //!
-//! Specifying which objects should be cloned and where may be added in the
-//! future, but will probably require a breaking change.
+//! Objects which are not `Copy` may still be used anywhere in the comprehension
+//! by listing them after `ref` at its start, as described below. Specifying
+//! which objects should be cloned and where may be added in the future, but
+//! will probably require a breaking change.
 //!
 //! This is a BNF description of the syntax used by this macro:
 //!
 //! ```bnf
-//! comprehension ::=  expression ";" comp_for [comp_iter] [";"]
-//! comp_iter     ::=  ";" (comp_for | comp_if | comp_if_let)
-//! comp_for      ::=  "for" pattern "in" expression [comp_iter]
+//! comprehension ::=  [comp_ref] [comp_lineage] expression ";" comp_for [comp_iter] [";"]
+//! comp_ref      ::=  "ref" identifier ("," identifier)* ";"
+//! comp_lineage  ::=  "with" ["full"] "lineage"
+//! comp_iter     ::=  ";" (comp_for | comp_if | comp_if_let | comp_slice | comp_scan)
+//! comp_for      ::=  "for" ["!"] pattern "in" comp_source [comp_iter]
+//! comp_source   ::=  "fixed" expression | [comp_modifier] expression
+//! comp_modifier ::=  "window" size "of" | "pairwise" | comp_chunks
+//! comp_chunks   ::=  "chunks" (size [comp_partial] | "[" size "]" comp_partial) "of"
+//! comp_partial  ::=  "keep" | "drop" | "pad" value
 //! comp_if       ::=  "if" expression [comp_iter]
 //! comp_if_let   ::=  "if" "let" pattern ("|" pattern)* "=" expression [comp_iter]
+//! comp_slice    ::=  ("take" | "skip" | "step") expression [comp_iter]
+//! comp_scan     ::=  "scan" identifier "=" expression "=>" expression [comp_iter]
 //! ```
 //!
 //! Just like in Python, you can nest as many `for`, `if`, and `if let`
@@ -142,233 +198,3658 @@
 //! assert_eq!(expected_values, nested_objects);
 //! ```
 //!
+//! ## Borrowing
+//!
+//! Prefixing a comprehension with `ref` and a list of local variables makes every
+//! level capture a shared reference to them instead of moving them, so that
+//! collections which are not `Copy`, such as a `Vec<String>` or a `HashMap`, may
+//! be named in any `in` clause or in the yield expression. The comprehension then
+//! borrows these variables, and may not outlive them:
+//!
+//! ```rust
+//! use py_comp::comp;
+//! use std::collections::HashMap;
+//!
+//! let names = vec![String::from("alice"), String::from("bob")];
+//! let ages: HashMap<&str, u32> = vec![("alice", 31), ("bob", 27)].into_iter().collect();
+//!
+//! let pairs: Vec<(&String, u32)> = comp!(
+//!     ref names, ages;
+//!     (name, *age);
+//!     for (key, age) in ages;
+//!     for name in names;
+//!     if name == key;
+//! )
+//! .collect();
+//!
+//! assert_eq!(pairs.len(), 2);
+//! assert!(pairs.contains(&(&names[1], 27)));
+//! ```
+//!
+//! ## Owning
+//!
+//! The `comp_owned!` macro accepts the same syntax as `comp!`, preceded by `move`
+//! and a list of local collections. These are moved into reference-counted
+//! `Shared` pointers, which every level of the comprehension holds a share of,
+//! and which yield clones of their items when iterated over. The comprehension
+//! can therefore be returned from the function creating the collections, and
+//! sent to another thread when they are `Send` and `Sync`:
+//!
+//! ```rust
+//! use py_comp::comp_owned;
+//!
+//! fn grid(size: u32) -> impl Iterator<Item = (u32, u32)> + Send + 'static {
+//!     let axis: Vec<u32> = (0..size).collect();
+//!
+//!     comp_owned!(move axis; (x, y); for x in axis; for y in axis)
+//! }
+//!
+//! assert_eq!(grid(2).collect::<Vec<_>>(), vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+//! ```
+//!
+//! ## Re-iterating
+//!
+//! The `comp_def!` macro accepts the same syntax as `comp!`, optionally preceded
+//! by `move` and a list of local collections like `comp_owned!`, and evaluates to
+//! a `Def` which builds the comprehension again every time a reference to it is
+//! iterated over, like a Python generator function:
+//!
+//! ```rust
+//! use py_comp::comp_def;
+//!
+//! let limits = vec![("cpu", 4), ("memory", 2)];
+//!
+//! let slots = comp_def!(move limits; (name, slot); for (name, limit) in limits; for slot in 0..limit);
+//!
+//! for _ in 0..2 {
+//!     assert_eq!((&slots).into_iter().count(), 6);
+//! }
+//! ```
+//!
+//! ## Boxing
+//!
+//! The `comp_boxed!` and `comp_dyn!` macros accept the same syntax as `comp!`,
+//! and box the comprehension as a `Box<dyn Iterator<Item = T> + 'a>`, or a
+//! `Box<dyn Iterator<Item = T> + Send + 'a>` respectively, so that the
+//! comprehensions built by different branches have the same type. Preceding the
+//! clauses with `per level` boxes the comprehension nested in every `for` clause
+//! as well, which keeps the types of deeply nested comprehensions small:
+//!
+//! ```rust
+//! use py_comp::{boxed::Boxed, comp_boxed};
+//!
+//! fn pairs<'a>(xs: &'a [i32], distinct: bool) -> Boxed<'a, (i32, i32)> {
+//!     if distinct {
+//!         comp_boxed!(per level (*a, *b); for a in xs; for b in xs; if a != b)
+//!     } else {
+//!         comp_boxed!((*a, *a); for a in xs)
+//!     }
+//! }
+//!
+//! assert_eq!(pairs(&[1, 2], true).count(), 2);
+//! assert_eq!(pairs(&[1, 2], false).count(), 2);
+//! ```
+//!
+//! ## Naming
+//!
+//! The `comp_struct!` macro defines a named iterator type for a comprehension,
+//! which can be stored in struct fields or used as an associated type without
+//! boxing it. It lists the inputs of the comprehension, which become the
+//! arguments of its `new` function, and the type of its items. Every `for`
+//! clause is followed by `=>` and the type of its source, as is every `if let`
+//! clause followed by another `for` clause by the type of the value it matches,
+//! and only `for`, `if` and `if let` clauses are accepted:
+//!
+//! ```rust
+//! use py_comp::comp_struct;
+//!
+//! comp_struct! {
+//!     pub struct Pairs<'a>(xs: &'a [u32], ys: &'a [u32]) -> (u32, u32) {
+//!         (*x, *y);
+//!         for x in xs => &'a [u32];
+//!         for y in ys => &'a [u32];
+//!         if x < y;
+//!     }
+//! }
+//!
+//! struct Matcher<'a> {
+//!     pairs: Pairs<'a>,
+//! }
+//!
+//! let matcher = Matcher { pairs: Pairs::new(&[1, 2], &[2, 3]) };
+//! assert_eq!(matcher.pairs.count(), 3);
+//! ```
+//!
+//! ## Explaining
+//!
+//! The `comp_explain!` macro accepts the same syntax as `comp!`, and evaluates
+//! to a plan of how `comp!` expands the comprehension, without evaluating any of
+//! its expressions. The plan lists every level of nesting with its clauses, the
+//! rule of `comp!` which expands it, the iterator adapters it expands to and the
+//! closure passed to them. It is printed as an indented tree, and may be
+//! converted to a Graphviz graph with `to_dot`:
+//!
+//! ```rust
+//! use py_comp::comp_explain;
+//!
+//! let plan = comp_explain!((x, y); for x in 0..4; if x % 2 == 0; for y in 0..x);
+//!
+//! assert_eq!(plan.levels[0].pipeline, "into_iter().filter_map(..).flatten()");
+//!
+//! println!("{}", plan);
+//! println!("{}", plan.to_dot());
+//! ```
+//!
+//! ## Tracing
+//!
+//! With the optional `tracing` feature, `comp!` reports what it does through the
+//! [`tracing`] crate. Every `for` clause runs in a `comp` span, and every item it
+//! takes runs in a nested `comp_item` span, which records the item taken from
+//! the source in its `item` field, and every name bound by the pattern of the
+//! clause in a field of the same name. Every item rejected by the pattern of a
+//! `for` clause, or by an `if` or `if let` clause, emits a debug event inside the
+//! span of that item, with a field for every name bound by the earlier clauses
+//! of its level. All of them carry the source text of their clause in a `clause`
+//! field. The names are taken from the first alternative of a pattern, and a
+//! name which is not followed by parentheses or braces, such as a constant, is
+//! recorded as if it were bound. Without the feature, no spans or events are
+//! created, and the closures of `comp!` bind their patterns directly.
+//!
+//! ```toml
+//! [dependencies]
+//! py-comp = { version = "0.1", features = ["tracing"] }
+//! ```
+//!
+//! ## Lineage
+//!
+//! Prefixing the item expression of `comp!` with `with lineage` yields every
+//! item paired with an array of the index of the item of every `for` clause it
+//! came from, counting from zero for every item of the enclosing clauses. With
+//! `with full lineage`, the array also holds the index of the item among those
+//! let through by every `if` and `if let` clause. The index of a `for` clause
+//! counts the items its pattern does not match, so it is always the position of
+//! the item in the source:
+//!
+//! ```rust
+//! use py_comp::comp;
+//!
+//! let rows = &[vec![1, 2], vec![3], vec![4, 5, 6]];
+//!
+//! let items: Vec<(i32, [usize; 2])> =
+//!     comp!(with lineage *x; for row in rows; for x in row; if x % 2 == 0).collect();
+//!
+//! assert_eq!(items, vec![(2, [0, 1]), (4, [2, 0]), (6, [2, 2])]);
+//!
+//! let options = &[None, Some(1), None, Some(2)];
+//!
+//! let items: Vec<(i32, [usize; 1])> =
+//!     comp!(with lineage *x; for Some(x) in options).collect();
+//!
+//! assert_eq!(items, vec![(1, [1]), (2, [3])]);
+//! ```
+//!
+//! ## Reductions
+//!
+//! The `comp_sum!`, `comp_product!`, `comp_min!`, `comp_max!`, `comp_count!`,
+//! `comp_any!` and `comp_all!` macros accept the same syntax as `comp!`, and
+//! reduce the comprehension to a single value like the Python functions
+//! `sum`, `math.prod`, `min`, `max`, `len`, `any` and `all` do. The result has the
+//! same type as the items, so it needs no type annotations. `comp_any!` and
+//! `comp_all!` stop at the first decisive item, and `comp_min!` and `comp_max!`
+//! accept a `key =>` prefix to compare the items by a key:
+//!
+//! ```rust
+//! use py_comp::{comp_any, comp_max, comp_sum};
+//!
+//! let scores = &[("alice", 7), ("bob", 9), ("carol", 4)];
+//!
+//! assert_eq!(comp_sum!(*score; for (_, score) in scores), 20);
+//! assert_eq!(comp_max!(score => *name; for (name, score) in scores), Some("bob"));
+//! assert!(comp_any!(*score < 5; for (_, score) in scores));
+//! ```
+//!
+//! ## Searching
+//!
+//! The `comp_find!` macro accepts the same syntax as `comp!`, and evaluates to
+//! the first item of the comprehension, or `None`. It expands to plain nested
+//! `for` loops which are left as soon as an item passes all of the clauses,
+//! so the sources of nested levels may borrow any local variable:
+//!
+//! ```rust
+//! use py_comp::comp_find;
+//!
+//! let names = vec![String::from("alice"), String::from("bob")];
+//! let banned = vec![String::from("bob")];
+//!
+//! let found = comp_find!(name; for name in &names; for ban in &banned; if name == ban);
+//!
+//! assert_eq!(found, Some(&names[1]));
+//! ```
+//!
+//! ## Loops
+//!
+//! The `comp_for_each!` macro accepts the same clauses as `comp!`, followed by
+//! `=>` and a block instead of the leading item expression. It expands to plain
+//! nested `for` loops running the block, which may therefore mutably borrow
+//! local variables, `return` from the enclosing function, or `break` out of an
+//! enclosing labeled loop:
+//!
+//! ```rust
+//! use py_comp::comp_for_each;
+//!
+//! let mut grid = vec![vec![0; 3]; 3];
+//!
+//! comp_for_each!(for row in 0..3; for col in 0..3; if row != col => {
+//!     grid[row][col] = row * 3 + col;
+//! });
+//!
+//! assert_eq!(grid, vec![vec![0, 1, 2], vec![3, 0, 5], vec![6, 7, 0]]);
+//! ```
+//!
+//! ## Profiling
+//!
+//! The `comp_profiled!` macro accepts the same syntax as `comp!`, and evaluates
+//! to the comprehension together with a report, which records how many items
+//! every `for`, `if` and `if let` clause received and let through, and the time
+//! spent in it, as the comprehension is iterated. The report is printed as a
+//! table with the source text of every clause:
+//!
+//! ```rust
+//! use py_comp::comp_profiled;
+//!
+//! let (items, report) = comp_profiled!(x; for x in 0..100; if x % 7 == 0; if x % 2 == 0);
+//! assert_eq!(items.count(), 8);
+//!
+//! println!("{}", report);
+//! ```
+//!
+//! ## Resuming
+//!
+//! The `comp_resumable!` macro accepts the same syntax as `comp!`, except for the
+//! `take`, `skip`, `step` and `scan` clauses. It evaluates to an iterator whose
+//! `cursor()` is a small value holding one index per `for` clause, which can be
+//! stored as text and passed to `resume` on the same comprehension built again,
+//! to continue after a restart:
+//!
+//! ```rust
+//! use py_comp::{comp_resumable, cursor::Cursor};
+//!
+//! let mut pairs = comp_resumable!((x, y); for x in 0..3; for y in 0..3);
+//! pairs.nth(4);
+//! let checkpoint = pairs.cursor().to_string();
+//!
+//! let cursor: Cursor = checkpoint.parse().unwrap();
+//! let mut resumed = comp_resumable!((x, y); for x in 0..3; for y in 0..3).resume(&cursor);
+//!
+//! assert_eq!(resumed.next(), Some((1, 2)));
+//! ```
+//!
+//! ## Random access
+//!
+//! The `comp_indexed!` macro accepts the same syntax as `comp!`, limited to
+//! `for` clauses over slices and ranges which do not depend on each other. It
+//! evaluates to an iterator which computes any item from its position, so it
+//! supports constant-time `nth`, `get(i)`, `len()` and `split_at(i)`:
+//!
+//! ```rust
+//! use py_comp::comp_indexed;
+//!
+//! let mut pairs = comp_indexed!((x, y); for x in 0..1000; for y in 0..1000);
+//!
+//! assert_eq!(pairs.get(2024), Some((2, 24)));
+//! assert_eq!(pairs.nth(123_456), Some((123, 456)));
+//! assert_eq!(pairs.len(), 876_543);
+//! ```
+//!
+//! ## Extending
+//!
+//! The `comp_extend!` macro accepts the same syntax as `comp!`, preceded by an
+//! existing collection, and appends the items of the comprehension to it. It
+//! reserves capacity for the lower bound of the comprehension's `size_hint`
+//! first, and never allocates an intermediate collection:
+//!
+//! ```rust
+//! use py_comp::comp_extend;
+//!
+//! let mut squares = vec![0];
+//! comp_extend!(squares; x * x; for x in 1..4);
+//!
+//! assert_eq!(squares, vec![0, 1, 4, 9]);
+//! ```
+//!
+//! ## Partitioning
+//!
+//! The `comp_partition!` and `comp_unzip!` macros accept the same syntax as
+//! `comp!`, and fill two or more collections implementing `Default` and `Extend`
+//! in a single pass over nested loops. `comp_partition!` takes a leading
+//! condition deciding which of two collections each item goes into, and
+//! `comp_unzip!` takes an item expression evaluating to a tuple whose elements
+//! go into a tuple of collections. The types of the collections may be given
+//! after `=>`:
+//!
+//! ```rust
+//! use py_comp::{comp_partition, comp_unzip};
+//!
+//! let (even, odd) = comp_partition!(x % 2 == 0 => (Vec<_>, Vec<_>); x; for x in 0..5);
+//! let (xs, squares): (Vec<_>, Vec<_>) = comp_unzip!((x, x * x); for x in 0..3);
+//!
+//! assert_eq!((even, odd), (vec![0, 2, 4], vec![1, 3]));
+//! assert_eq!((xs, squares), (vec![0, 1, 2], vec![0, 1, 4]));
+//! ```
+//!
 //! [`generator-expression`]: https://docs.python.org/3/reference/expressions.html#generator-expressions
+//! [`tracing`]: https://docs.rs/tracing
 //!
 
-#![warn(clippy::all)]
+#![warn(clippy::all)]
+
+use doc_comment::doctest;
+
+doctest!("../Readme.md");
+
+pub mod adapters;
+pub mod boxed;
+pub mod cursor;
+pub mod def;
+pub mod explain;
+pub mod extend;
+pub mod indexed;
+pub mod named;
+pub mod product;
+pub mod profile;
+pub mod shared;
+#[cfg(feature = "tracing")]
+pub mod trace;
+
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+pub use tracing as __tracing;
+
+/// A type which may be iterated over by a `for` clause.
+///
+/// This is implemented for every `IntoIterator`, and only exists to explain
+/// the error when the `in` expression of a `for` clause is not one.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can not be iterated over by a `for` clause of a comprehension",
+    label = "the `in` expression of this `for` clause must implement `IntoIterator`",
+    note = "a `for` clause iterates over its `in` expression like a `for` loop does, so a \
+            `{Self}` must first be borrowed, dereferenced, or turned into an iterator by one \
+            of its methods"
+)]
+pub trait __PyCompIntoIterator: IntoIterator {}
+
+impl<T: IntoIterator> __PyCompIntoIterator for T {}
+
+/// Check that the `in` expression of a `for` clause implements IntoIterator.
+///
+/// This is a struct rather than a function, so that temporaries borrowed by
+/// the expression live as long as the variable it is bound to.
+#[doc(hidden)]
+pub struct __PyCompSource<T: __PyCompIntoIterator> {
+    pub into_iterator: T,
+}
+
+/// Turn the checked `in` expression of a `for` clause into an iterator.
+///
+/// When the check fails, the method of `__PyCompIntoNothing` is picked
+/// instead, so that the clause is only reported once.
+#[doc(hidden)]
+pub struct __PyCompChecked<T>(pub T);
+
+#[doc(hidden)]
+pub trait __PyCompIntoIter {
+    type IntoIter: Iterator;
+
+    fn __py_comp_into_iter(self) -> Self::IntoIter;
+}
+
+impl<T: IntoIterator> __PyCompIntoIter for __PyCompChecked<T> {
+    type IntoIter = T::IntoIter;
+
+    #[inline(always)]
+    fn __py_comp_into_iter(self) -> T::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[doc(hidden)]
+pub trait __PyCompIntoNothing {
+    fn __py_comp_into_iter<U>(self) -> core::iter::Empty<U>;
+}
+
+impl<T> __PyCompIntoNothing for &__PyCompChecked<T> {
+    #[inline(always)]
+    fn __py_comp_into_iter<U>(self) -> core::iter::Empty<U> {
+        core::iter::empty()
+    }
+}
+
+/// Sum the items of an iterator into a value of the same type.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __py_comp_sum<T: core::iter::Sum<T>>(iter: impl Iterator<Item = T>) -> T {
+    iter.sum()
+}
+
+/// Multiply the items of an iterator into a value of the same type.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __py_comp_product<T: core::iter::Product<T>>(iter: impl Iterator<Item = T>) -> T {
+    iter.product()
+}
+
+/// Find the item with the minimum key in an iterator of `(key, item)` pairs.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __py_comp_min_by_key<K: Ord, T>(iter: impl Iterator<Item = (K, T)>) -> Option<T> {
+    iter.min_by(|(a, _), (b, _)| a.cmp(b)).map(|(_, item)| item)
+}
+
+/// Find the first maximum item of an iterator.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __py_comp_max<T: Ord>(iter: impl Iterator<Item = T>) -> Option<T> {
+    iter.max_by(|a, b| a.cmp(b).then(core::cmp::Ordering::Greater))
+}
+
+/// Find the first item with the maximum key in an iterator of `(key, item)`
+/// pairs.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __py_comp_max_by_key<K: Ord, T>(iter: impl Iterator<Item = (K, T)>) -> Option<T> {
+    iter.max_by(|(a, _), (b, _)| a.cmp(b).then(core::cmp::Ordering::Greater))
+        .map(|(_, item)| item)
+}
+
+/// Wrap the iterator of a `for` clause in a span, when the `tracing` feature
+/// is enabled.
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_level {
+    ([$($clause: tt)*] $iterator: expr) => {
+        $crate::trace::Traced::new(
+            $crate::__tracing::debug_span!("comp", clause = ::core::stringify!($($clause)*)),
+            $iterator,
+        )
+    };
+}
+
+/// Wrap the iterator of a `for` clause in a span, when the `tracing` feature
+/// is enabled.
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_level {
+    ([$($clause: tt)*] $iterator: expr) => {
+        $iterator
+    };
+}
+
+/// Create the closure of a `for` clause, which runs in a span recording the
+/// item and the names bound by its pattern when the `tracing` feature is
+/// enabled. A level whose pattern may be refutable binds `item` instead, so
+/// its names are recorded by the clause which matches the pattern.
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! __py_comp_trace_closure {
+    ([$($clause: tt)*] [$kind: ident $($name: ident)*] |$pattern: pat| $body: expr) => {
+        move |item| {
+            #[allow(unused_imports)]
+            use $crate::trace::{__PyCompDebugKind, __PyCompOpaqueKind};
+            let span = $crate::__tracing::trace_span!(
+                "comp_item",
+                clause = ::core::stringify!($($clause)*),
+                item = ?(&$crate::trace::__PyCompItem(&item)).__py_comp_debug(),
+                $($name = $crate::__tracing::field::Empty,)*
+            );
+            let _entered = span.enter();
+            let $pattern = item;
+            __py_comp_trace_closure!(@record [$kind $($name)*]);
+            $body
+        }
+    };
+
+    (@record [bind $($name: ident)*]) => {
+        __py_comp_trace_record!([$($name)*])
+    };
+
+    (@record [$kind: ident $($name: ident)*]) => {};
+}
+
+/// Create the closure of a `for` clause, which runs in a span recording the
+/// item and the names bound by its pattern when the `tracing` feature is
+/// enabled.
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_closure {
+    ([$($clause: tt)*] $names: tt |$pattern: pat| $body: expr) => {
+        move |$pattern| $body
+    };
+}
+
+/// Record the names bound by the pattern of a `for` clause in the span of the
+/// current item, when the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_record {
+    ([$($name: ident)*]) => {{
+        #[allow(unused_imports)]
+        use $crate::trace::{__PyCompDebugKind, __PyCompOpaqueKind};
+        let span = $crate::__tracing::Span::current();
+        $(
+            span.record(
+                ::core::stringify!($name),
+                &$crate::__tracing::field::debug(
+                    (&$crate::trace::__PyCompItem(&$name)).__py_comp_debug(),
+                ),
+            );
+        )*
+    }};
+}
+
+/// Record the names bound by the pattern of a `for` clause in the span of the
+/// current item, when the `tracing` feature is enabled.
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_record {
+    ($names: tt) => {};
+}
+
+/// Emit an event for an item rejected by the pattern of a `for` clause, or by
+/// an `if` or `if let` clause, with the names bound before that clause, when
+/// the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_reject {
+    ([$($name: ident)*] $($clause: tt)*) => {{
+        #[allow(unused_imports)]
+        use $crate::trace::{__PyCompDebugKind, __PyCompOpaqueKind};
+        $crate::__tracing::debug!(
+            clause = ::core::stringify!($($clause)*),
+            $($name = ?(&$crate::trace::__PyCompItem(&$name)).__py_comp_debug(),)*
+            "comprehension item rejected"
+        )
+    }};
+}
+
+/// Emit an event for an item rejected by the pattern of a `for` clause, or by
+/// an `if` or `if let` clause, with the names bound before that clause, when
+/// the `tracing` feature is enabled.
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_trace_reject {
+    ($($clause: tt)*) => {};
+}
+
+/// Compute the length of the nested levels of a `for` clause followed by
+/// another one, from the tokens of the nested levels. The length is only
+/// known in advance when every nested level is a `for` clause with no other
+/// clauses, whose pattern binds every item and whose source is either marked
+/// `fixed` or made of literals only, since it then does not depend on the
+/// enclosing items.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! __py_comp_nested_len {
+    // @level
+    (@level [$($sources: tt)*]) => {
+        $crate::product::Independent::new(&[$(
+            ::core::iter::Iterator::size_hint(
+                &::core::iter::IntoIterator::into_iter($sources)
+            )
+        ),*])
+    };
+
+    (@level $sources: tt for $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@level $sources: tt $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    // @pattern
+    // These rules mirror `@classify` in `__py_comp_parse!`.
+    (@pattern $sources: tt in $($rest: tt)*) => {
+        __py_comp_nested_len!(@source $sources [] $($rest)*)
+    };
+
+    (@pattern $sources: tt None $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@pattern $sources: tt $literal: literal $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@pattern $sources: tt $name: ident :: $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@pattern $sources: tt $name: ident ($($fields: tt)*) $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@pattern $sources: tt $name: ident { $($fields: tt)* } $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@pattern $sources: tt $name: ident $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt ($($fields: tt)*) $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($fields)* $($rest)*)
+    };
+
+    (@pattern $sources: tt _ $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt & $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt && $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt , $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt .. $($rest: tt)*) => {
+        __py_comp_nested_len!(@pattern $sources $($rest)*)
+    };
+
+    (@pattern $sources: tt $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    // @source
+    // These rules collect the source up to the next `;`, and check it.
+    (@source $sources: tt [$($source: tt)*] ; $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources [$($source)*] [$($source)*] $($rest)*)
+    };
+
+    (@source $sources: tt [$($source: tt)*] $token: tt $($rest: tt)*) => {
+        __py_comp_nested_len!(@source $sources [$($source)* $token] $($rest)*)
+    };
+
+    (@source $sources: tt [$($source: tt)*]) => {
+        __py_comp_nested_len!(@literal $sources [$($source)*] [$($source)*])
+    };
+
+    // @literal
+    // A source marked `fixed` is trusted not to depend on the enclosing
+    // items, and a source without any names can neither refer to them nor
+    // call anything, so either is evaluated once to compute the length.
+    (@literal [$($sources: tt)*] [fixed $source: expr] $tokens: tt $($rest: tt)*) => {
+        __py_comp_nested_len!(@level [$($sources)* ($source)] $($rest)*)
+    };
+
+    (@literal [$($sources: tt)*] [$($source: tt)*] [] $($rest: tt)*) => {
+        __py_comp_nested_len!(@level [$($sources)* ($($source)*)] $($rest)*)
+    };
+
+    (@literal $sources: tt $source: tt [$literal: literal $($tokens: tt)*] $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources $source [$($tokens)*] $($rest)*)
+    };
+
+    (@literal $sources: tt $source: tt [$name: ident $($tokens: tt)*] $($rest: tt)*) => {
+        $crate::product::Dependent
+    };
+
+    (@literal $sources: tt $source: tt [($($group: tt)*) $($tokens: tt)*] $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources $source [$($group)* $($tokens)*] $($rest)*)
+    };
+
+    (@literal $sources: tt $source: tt [[$($group: tt)*] $($tokens: tt)*] $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources $source [$($group)* $($tokens)*] $($rest)*)
+    };
+
+    (@literal $sources: tt $source: tt [{ $($group: tt)* } $($tokens: tt)*] $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources $source [$($group)* $($tokens)*] $($rest)*)
+    };
+
+    (@literal $sources: tt $source: tt [$token: tt $($tokens: tt)*] $($rest: tt)*) => {
+        __py_comp_nested_len!(@literal $sources $source [$($tokens)*] $($rest)*)
+    };
+
+    ($($rest: tt)+) => {
+        __py_comp_nested_len!(@level [] $($rest)+)
+    };
+}
+
+/// A Python-like lazy generator-expression
+///
+/// For details see [module level documentation][super]
+///
+/// [super]: ../py_comp/index.html
+#[macro_export(local_inner_macros)]
+macro_rules! comp {
+    // @clauses
+    // This case yields the item at the end of the final `for` clause.
+    (@clauses [$item_expr: expr] $bound: tt []) => {
+        Some($item_expr)
+    };
+
+    // @clauses for ...
+    // This case returns to the main macro parsing.
+    (@clauses [$item_expr: expr] $bound: tt [for $($rest: tt)*]) => {
+        Some(comp!($item_expr; for $($rest)*))
+    };
+
+    // @clauses if ...
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { if $condition: expr }
+        $($clause: tt)*
+    ) => {
+        if $condition {
+            comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+        } else {
+            __py_comp_trace_reject!($bound if $condition);
+            None
+        }
+    };
+
+    // @clauses if-let ...
+    (@clauses
+        [$item_expr: expr] [$($bound: ident)*] $rest: tt
+        { if let [$($if_let_name: ident)*] $( $if_let_pattern: pat )|+ = $if_let_expr: expr }
+        $($clause: tt)*
+    ) => {
+        if let $( $if_let_pattern )|+ = $if_let_expr {
+            comp!(@clauses [$item_expr] [$($bound)* $($if_let_name)*] $rest $($clause)*)
+        } else {
+            __py_comp_trace_reject!([$($bound)*] if let $( $if_let_pattern )|+ = $if_let_expr);
+            None
+        }
+    };
+
+    // @clauses match ...
+    // This clause matches the items of a `for` clause whose pattern may be
+    // refutable, and skips the items it does not match.
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { match $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => {
+                __py_comp_trace_record!($bound);
+                comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                __py_comp_trace_reject!([] for $( $pattern )|+);
+                None
+            }
+        }
+    };
+
+    // @clauses expect ...
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { expect $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => {
+                __py_comp_trace_record!($bound);
+                comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+            }
+            #[allow(unreachable_patterns)]
+            _ => __py_comp_expect_failed!($( $pattern )|+),
+        }
+    };
+
+    // @clauses take ...
+    // The level stops once the count reaches the limit, before pulling
+    // another item, so the count never exceeds it here.
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { take $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {{
+        $count += 1;
+        comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+    }};
+
+    // @clauses skip ...
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { skip $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {
+        if $count < $limit {
+            $count += 1;
+            None
+        } else {
+            comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+        }
+    };
+
+    // @clauses step ...
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { step $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {{
+        let due = $count == 0;
+        $count = ($count + 1) % $limit;
+        if due {
+            comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+        } else {
+            None
+        }
+    }};
+
+    // @clauses scan ...
+    // The update expression receives the previous value of the accumulator
+    // by value, and a clone of its new value is kept for the next item.
+    (@clauses
+        [$item_expr: expr] $bound: tt $rest: tt
+        { scan $state: ident $acc: ident = $update: expr }
+        $($clause: tt)*
+    ) => {{
+        let $acc = $state.take().unwrap();
+        let $acc = $update;
+        $state = Some(::core::clone::Clone::clone(&$acc));
+        comp!(@clauses [$item_expr] $bound $rest $($clause)*)
+    }};
+
+    // @emit
+    // This case adds the source text of the `for` clause to the levels which
+    // bind its pattern.
+    (@emit [$item_expr: tt [$pattern: pat] [$into_iterator: expr] $names: tt] $($rest: tt)*) => {
+        comp!(@emit
+            [$item_expr [$pattern] [$into_iterator] [for $pattern in $into_iterator] $names]
+            $($rest)*
+        )
+    };
+
+    // for in
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [] [] [] []
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        __py_comp_trace_level!([$($clause_source)*] into_iterator
+            .into_iter()
+            .map(__py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern| $item_expr))
+        )
+    }};
+
+    // for in for ...
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [] [] [] [$($rest: tt)+]
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        let len = __py_comp_nested_len!($($rest)+);
+        __py_comp_trace_level!([$($clause_source)*] $crate::product::product(
+            into_iterator.into_iter(),
+            __py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern|
+                comp!($item_expr; $($rest)+)
+            ),
+            len,
+        ))
+    }};
+
+    // for in $( if | if-let | skip | step | scan )+
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [$($state: tt)*] [$($clause: tt)+] [] []
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        __py_comp_trace_level!([$($clause_source)*] into_iterator
+            .into_iter()
+            .filter_map(__py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern|
+                comp!(@clauses [$item_expr] [$($name)*] [] $($clause)+)
+            ))
+        )
+    }};
+
+    // for in $( if | if-let | skip | step | scan )+ for ...
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [$($state: tt)*] [$($clause: tt)+] [] [$($rest: tt)+]
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        __py_comp_trace_level!([$($clause_source)*] into_iterator
+            .into_iter()
+            .filter_map(__py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern|
+                comp!(@clauses [$item_expr] [$($name)*] [$($rest)+] $($clause)+)
+            ))
+            .flatten()
+        )
+    }};
+
+    // for in $( if | if-let | take | skip | step | scan )+
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [$($state: tt)*] [$($clause: tt)+] [$({ $count: ident $limit: ident })+] []
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        __py_comp_trace_level!([$($clause_source)*] $crate::adapters::until(
+            into_iterator,
+            $($count == $limit)||+,
+            __py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern| (
+                comp!(@clauses [$item_expr] [$($name)*] [] $($clause)+),
+                $($count == $limit)||+,
+            )),
+        )
+        .flatten())
+    }};
+
+    // for in $( if | if-let | take | skip | step | scan )+ for ...
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] [$($clause_source: tt)*]
+         [$kind: ident $($name: ident)*]]
+        [$($state: tt)*] [$($clause: tt)+] [$({ $count: ident $limit: ident })+] [$($rest: tt)+]
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        __py_comp_trace_level!([$($clause_source)*] $crate::adapters::until(
+            into_iterator,
+            $($count == $limit)||+,
+            __py_comp_trace_closure!([$($clause_source)*] [$kind $($name)*] |$pattern| (
+                comp!(@clauses [$item_expr] [$($name)*] [$($rest)+] $($clause)+),
+                $($count == $limit)||+,
+            )),
+        )
+        .flatten()
+            .flatten())
+    }};
+
+    // @lineage
+    // These rules split the clauses at every `;`, and add a `@count` clause
+    // counting the items after every `for` clause, as well as after every
+    // `if` clause in `full` mode. The counters of these clauses are
+    // collected to be yielded with the item.
+    (@lineage $mode: tt $item: tt $indices: tt [$($clauses: tt)*]) => {
+        comp!(@lineage_item $item $indices $($clauses)*)
+    };
+
+    (@lineage $mode: tt $item: tt $indices: tt [$($clauses: tt)*] for $($rest: tt)*) => {
+        comp!(@lineage_clause $mode counted $item $indices [$($clauses)* for] $($rest)*)
+    };
+
+    (@lineage full $item: tt $indices: tt [$($clauses: tt)*] if $($rest: tt)*) => {
+        comp!(@lineage_clause full counted $item $indices [$($clauses)* if] $($rest)*)
+    };
+
+    (@lineage $mode: tt $item: tt $indices: tt [$($clauses: tt)*] $($rest: tt)+) => {
+        comp!(@lineage_clause $mode uncounted $item $indices [$($clauses)*] $($rest)+)
+    };
+
+    (@lineage_clause $mode: tt counted $item: tt [$($index: ident)*] [$($clauses: tt)*]
+        $(; $($rest: tt)*)?
+    ) => {
+        comp!(@lineage $mode $item [$($index)* index]
+            [$($clauses)*; @count index;]
+            $($($rest)*)?
+        )
+    };
+
+    (@lineage_clause $mode: tt uncounted $item: tt $indices: tt [$($clauses: tt)*]
+        $(; $($rest: tt)*)?
+    ) => {
+        comp!(@lineage $mode $item $indices [$($clauses)*;] $($($rest)*)?)
+    };
+
+    (@lineage_clause $mode: tt $counted: tt $item: tt $indices: tt [$($clauses: tt)*]
+        $token: tt $($rest: tt)*
+    ) => {
+        comp!(@lineage_clause $mode $counted $item $indices [$($clauses)* $token] $($rest)*)
+    };
+
+    (@lineage_item [$item_expr: expr] [$($index: ident)*] $($clauses: tt)*) => {
+        comp!(($item_expr, [$($index),*]); $($clauses)*)
+    };
+
+    // with lineage
+    (with lineage $item_expr: expr; for $($rest: tt)*) => {
+        comp!(@lineage for [$item_expr] [] [] for $($rest)*)
+    };
+
+    // with full lineage
+    (with full lineage $item_expr: expr; for $($rest: tt)*) => {
+        comp!(@lineage full [$item_expr] [] [] for $($rest)*)
+    };
+
+    // ref
+    // Every named local is shadowed by a shared reference to it, which the
+    // `move` closures of every level copy instead of moving the local.
+    (ref $($local: ident),+; $($rest: tt)*) => {{
+        $(let $local = &$local;)+
+        comp!($($rest)*)
+    }};
+
+    // This case comes before the main entry point, where `for` would be
+    // parsed as the start of a `for` loop expression.
+    (for $($rest: tt)*) => {
+        __py_comp_error!(@at "expected a yield expression before the first `for` clause")
+    };
+
+    // The main entry point. The clauses are parsed by `__py_comp_parse`, which
+    // hands every level back to the `@emit` rules.
+    ($item_expr: expr; for $($rest: tt)*) => {
+        __py_comp_parse!(comp [$item_expr] for $($rest)*)
+    };
+
+    ($($rest: tt)*) => {
+        __py_comp_error!(@yield $($rest)*)
+    };
+}
+
+/// Parse the clauses of a comprehension, one level of nesting at a time.
+///
+/// This is shared by all the comprehension macros, so they accept exactly
+/// the same syntax. It is invoked as `__py_comp_parse!(target [head] for ...)`,
+/// and expands every level to `target!(@emit ...)`, where `head` is passed
+/// through to the `target` macro untouched.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! __py_comp_parse {
+    // @level ... @count
+    // This clause is only produced by the `with lineage` rules of `comp!`. It
+    // counts the items of its level, and is moved before the match of a
+    // pattern which may be refutable, so the skipped items are counted too.
+    (@level $target: tt $head: tt [$($state: tt)*] [{ $kind: ident $($pattern: tt)* }] $stop: tt
+        @count $index: ident
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [$($state)* let mut state = Some(usize::MAX);]
+            [{ scan state $index = $index.wrapping_add(1) } { $kind $($pattern)* }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    (@level $target: tt $head: tt [$($state: tt)*] [$($clause: tt)*] $stop: tt
+        @count $index: ident
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [$($state)* let mut state = Some(usize::MAX);]
+            [$($clause)* { scan state $index = $index.wrapping_add(1) }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    // @level ... if-let
+    // The pattern is collected up to its `=`, so the names it binds can be
+    // collected before it is parsed.
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt if let $($rest: tt)*) => {
+        __py_comp_parse!(@if_let [$target $head $state $clauses $stop] [] $($rest)*)
+    };
+
+    (@if_let $level: tt $pattern: tt = $($rest: tt)*) => {
+        __py_comp_parse!(@names [] $pattern [] [@if_let_bound $level $pattern $($rest)*])
+    };
+
+    (@if_let $level: tt [$($pattern: tt)*] $token: tt $($rest: tt)*) => {
+        __py_comp_parse!(@if_let $level [$($pattern)* $token] $($rest)*)
+    };
+
+    (@if_let $level: tt [$($pattern: tt)*]) => {
+        __py_comp_error!(@find "expected `;` after `if let` clause" $($pattern)*)
+    };
+
+    (@bound $names: tt @if_let_bound
+        [$target: tt $head: tt $state: tt [$($clause: tt)*] $stop: tt]
+        [$( $if_let_pattern: pat )|+] $if_let_expr: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head $state
+            [$($clause)* { if let $names $( $if_let_pattern )|+ = $if_let_expr }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    (@bound $names: tt @if_let_bound $level: tt [$($pattern: tt)*] $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `if let` clause" $($pattern)* = $($rest)*)
+    };
+
+    // @level ... if Pattern(...) = ...
+    // This case catches an `if let` clause missing its `let`, which would
+    // otherwise be parsed as an assignment.
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt
+        if $variant: tt $(:: $path: ident)* ($($fields: tt)*) = $($rest: tt)*
+    ) => {
+        __py_comp_error!(@at
+            "expected a condition, found an assignment; did you mean `if let`?"
+            $variant
+        )
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt
+        if $variant: tt $(:: $path: ident)* { $($fields: tt)* } = $($rest: tt)*
+    ) => {
+        __py_comp_error!(@at
+            "expected a condition, found an assignment; did you mean `if let`?"
+            $variant
+        )
+    };
+
+    // @level ... if
+    (@level $target: tt $head: tt $state: tt [$($clause: tt)*] $stop: tt
+        if $condition: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head $state
+            [$($clause)* { if $condition }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    // @level ... take
+    // The counters of `take`, `skip` and `step` clauses are declared once per
+    // instantiation of the level, so each `count` and `limit` introduced here
+    // is a distinct hygienic identifier.
+    (@level $target: tt $head: tt [$($state: tt)*] [$($clause: tt)*] [$($stop: tt)*]
+        take $limit_expr: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [$($state)* let mut count: usize = 0; let limit: usize = $limit_expr;]
+            [$($clause)* { take count limit }]
+            [$($stop)* { count limit }]
+            $($($rest)*)?
+        )
+    };
+
+    // @level ... skip
+    (@level $target: tt $head: tt [$($state: tt)*] [$($clause: tt)*] $stop: tt
+        skip $limit_expr: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [$($state)* let mut count: usize = 0; let limit: usize = $limit_expr;]
+            [$($clause)* { skip count limit }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    // @level ... step
+    (@level $target: tt $head: tt [$($state: tt)*] [$($clause: tt)*] $stop: tt
+        step $limit_expr: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [
+                $($state)*
+                let mut count: usize = 0;
+                let limit: usize = $limit_expr;
+                ::core::assert!(limit != 0, "`step` clause must not be zero");
+            ]
+            [$($clause)* { step count limit }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    // @level ... scan
+    (@level $target: tt $head: tt [$($state: tt)*] [$($clause: tt)*] $stop: tt
+        scan $acc: ident = $init: expr => $update: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@level $target $head
+            [$($state)* let mut state = Some($init);]
+            [$($clause)* { scan state $acc = $update }]
+            $stop
+            $($($rest)*)?
+        )
+    };
+
+    // @level [for ...]
+    // This case ends the clauses of the current level.
+    (@level comp $head: tt $state: tt $clauses: tt $stop: tt $(for $($rest: tt)*)?) => {
+        comp!(@emit $head $state $clauses $stop [$(for $($rest)*)?])
+    };
+
+    // @level [for ...]
+    (@level loops $head: tt $state: tt $clauses: tt $stop: tt $(for $($rest: tt)*)?) => {
+        __py_comp_loops!(@emit $head $state $clauses $stop [$(for $($rest)*)?])
+    };
+
+    // @level [for ...]
+    (@level explain $head: tt $state: tt $clauses: tt $stop: tt $(for $($rest: tt)*)?) => {
+        comp_explain!(@emit $head $state $clauses $stop [$(for $($rest)*)?])
+    };
+
+    // @level [for ...]
+    (@level resumable $head: tt $state: tt $clauses: tt $stop: tt $(for $($rest: tt)*)?) => {
+        comp_resumable!(@emit $head $clauses [$(for $($rest)*)?])
+    };
+
+    // @level ... error
+    // The following cases report the clause which could not be parsed.
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt if $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `if` condition" $($rest)*)
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt take $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `take` clause" $($rest)*)
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt skip $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `skip` clause" $($rest)*)
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt step $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `step` clause" $($rest)*)
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt scan $($rest: tt)*) => {
+        __py_comp_error!(@find
+            "expected `scan accumulator = initial => update` followed by `;`"
+            $($rest)*
+        )
+    };
+
+    (@level $target: tt $head: tt $state: tt $clauses: tt $stop: tt $token: tt $($rest: tt)*) => {
+        __py_comp_error!(@clause $token [$token])
+    };
+
+    // @pattern
+    // These rules collect the pattern of a `for` clause up to its `in`, so
+    // it can be classified before it is parsed.
+    (@pattern $target: tt $head: tt $kind: tt [$($pattern: tt)*] in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head $kind [$($pattern)*] [$($pattern)*] in $($rest)*)
+    };
+
+    (@pattern $target: tt $head: tt $kind: tt [$($pattern: tt)*] $token: tt $($rest: tt)*) => {
+        __py_comp_parse!(@pattern $target $head $kind [$($pattern)* $token] $($rest)*)
+    };
+
+    (@pattern $target: tt $head: tt $kind: tt [$($pattern: tt)*]) => {
+        __py_comp_error!(@for_pattern [$($pattern)*] $($pattern)*)
+    };
+
+    // @classify
+    // A pattern made only of names, `_`, references and tuples matches every
+    // item, so it is bound by the closure of its level. Any other pattern may
+    // be refutable, so the items are bound to `item` instead, and matched
+    // against the pattern by the first clause of the level.
+    (@classify $target: tt $head: tt [expect] $tokens: tt $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@for $target $head [expect] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@for $target $head [bind] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [None $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [$literal: literal $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [$name: ident :: $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [$name: ident ($($fields: tt)*) $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [$name: ident { $($fields: tt)* } $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [$name: ident $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt []
+        [($($fields: tt)*) $($tokens: tt)*] $pattern: tt in $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@classify $target $head [] [$($fields)* $($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [_ $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [& $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [&& $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [, $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] [.. $($tokens: tt)*] $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@classify $target $head [] [$($tokens)*] $pattern in $($rest)*)
+    };
+
+    (@classify $target: tt $head: tt [] $tokens: tt $pattern: tt in $($rest: tt)*) => {
+        __py_comp_parse!(@for $target $head [match] $pattern in $($rest)*)
+    };
+
+    // @bind
+    // The names bound by the pattern are collected before it is parsed, since
+    // the spans and events of the `tracing` feature record them.
+    (@bind $target: tt $head: tt $kind: tt $pattern: tt $($rest: tt)*) => {
+        __py_comp_parse!(@names [] $pattern [] [$target $head $kind $pattern $($rest)*])
+    };
+
+    // @bound
+    // This case parses the pattern, and starts the clauses of its level.
+    (@bound [$($name: ident)*] $target: tt [$head: tt] [bind] [$pattern: pat] $source: tt
+        $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@level $target [[$head] [$pattern] $source [bind $($name)*]] [] [] [] $($rest)*)
+    };
+
+    // The source text of the clause is passed along, since the level binds
+    // `item` instead of its pattern.
+    (@bound [$($name: ident)*] $target: tt [$head: tt] [match] [$( $pattern: pat )|+]
+        [$($source: tt)*] $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@level $target
+            [[$head] [item] [$($source)*] [for $( $pattern )|+ in $($source)*] [match $($name)*]]
+            [] [{ match $( $pattern )|+ = item }] []
+            $($rest)*
+        )
+    };
+
+    (@bound [$($name: ident)*] $target: tt [$head: tt] [expect] [$( $pattern: pat )|+]
+        [$($source: tt)*] $($rest: tt)*
+    ) => {
+        __py_comp_parse!(@level $target
+            [[$head] [item] [$($source)*] [for $( $pattern )|+ in $($source)*] [expect $($name)*]]
+            [] [{ expect $( $pattern )|+ = item }] []
+            $($rest)*
+        )
+    };
+
+    (@bound $names: tt $target: tt $head: tt $kind: tt [$($pattern: tt)*] $($rest: tt)*) => {
+        __py_comp_error!(@for_pattern [$($pattern)* in] $($pattern)* in)
+    };
+
+    // @names
+    // These rules collect the names bound by the first alternative of a
+    // pattern, by skipping paths, field names, literals and `ref` or `mut`,
+    // and descending into every group. A name which is not followed by a group
+    // may also be a constant or a unit variant, which is collected as well,
+    // except for `None`. The groups left to visit are kept on a stack.
+    (@names $names: tt [] [] [$($then: tt)*]) => {
+        __py_comp_parse!(@bound $names $($then)*)
+    };
+
+    (@names $names: tt [] [$tokens: tt $($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names $tokens [$($stack)*] $then)
+    };
+
+    (@names $names: tt [| $($tokens: tt)*] [] [$($then: tt)*]) => {
+        __py_comp_parse!(@bound $names $($then)*)
+    };
+
+    (@names $names: tt [:: $name: ident $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [$name: ident :: $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [:: $($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [$field: ident : $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [$name: ident ($($fields: tt)*) $($tokens: tt)*] [$($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names [$($fields)*] [[$($tokens)*] $($stack)*] $then)
+    };
+
+    (@names $names: tt [$name: ident { $($fields: tt)* } $($tokens: tt)*] [$($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names [$($fields)*] [[$($tokens)*] $($stack)*] $then)
+    };
+
+    (@names $names: tt [$name: ident ! $arguments: tt $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [($($fields: tt)*) $($tokens: tt)*] [$($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names [$($fields)*] [[$($tokens)*] $($stack)*] $then)
+    };
+
+    (@names $names: tt [[$($fields: tt)*] $($tokens: tt)*] [$($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names [$($fields)*] [[$($tokens)*] $($stack)*] $then)
+    };
+
+    (@names $names: tt [{ $($fields: tt)* } $($tokens: tt)*] [$($stack: tt)*] $then: tt) => {
+        __py_comp_parse!(@names $names [$($fields)*] [[$($tokens)*] $($stack)*] $then)
+    };
+
+    (@names $names: tt [ref $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [mut $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [None $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [$literal: literal $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    (@names [$($names: tt)*] [$name: ident $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names [$($names)* $name] [$($tokens)*] $stack $then)
+    };
+
+    (@names $names: tt [$token: tt $($tokens: tt)*] $stack: tt $then: tt) => {
+        __py_comp_parse!(@names $names [$($tokens)*] $stack $then)
+    };
+
+    // for in fixed
+    // The marker only matters to the `size_hint` of the enclosing level, which
+    // finds it in the tokens of its nested levels, so it is dropped here.
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in fixed $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern [$into_iterator] $($($rest)*)?)
+    };
+
+    // for in window
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in window $size: tt of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::windows::<$size, _>($into_iterator)]
+            $($($rest)*)?
+        )
+    };
+
+    // for in pairwise
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in pairwise $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::pairwise($into_iterator)]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks [N] drop
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks [$size: tt] drop of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [
+                $crate::adapters::array_chunks::<$size, _, _>(
+                    $into_iterator,
+                    $crate::adapters::Discard,
+                )
+            ]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks [N] pad (...)
+    // The parentheses are stripped here to avoid `unused_parens` warnings.
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks [$size: tt] pad ($pad: expr) of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [
+                $crate::adapters::array_chunks::<$size, _, _>(
+                    $into_iterator,
+                    $crate::adapters::Pad($pad),
+                )
+            ]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks [N] pad
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks [$size: tt] pad $pad: tt of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [
+                $crate::adapters::array_chunks::<$size, _, _>(
+                    $into_iterator,
+                    $crate::adapters::Pad($pad),
+                )
+            ]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks [N] keep
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks [$size: tt] $(keep)? of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        ::core::compile_error!(
+            "chunks collected into arrays can not keep a partial final chunk, \
+             use `drop` or `pad value` instead"
+        )
+    };
+
+    // for in chunks drop
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks $size: tt drop of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::chunks($into_iterator, $size, $crate::adapters::Discard)]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks pad (...)
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks $size: tt pad ($pad: expr) of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::chunks($into_iterator, $size, $crate::adapters::Pad($pad))]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks pad
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks $size: tt pad $pad: tt of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::chunks($into_iterator, $size, $crate::adapters::Pad($pad))]
+            $($($rest)*)?
+        )
+    };
+
+    // for in chunks keep
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in chunks $size: tt $(keep)? of $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern
+            [$crate::adapters::chunks($into_iterator, $size, $crate::adapters::Keep)]
+            $($($rest)*)?
+        )
+    };
+
+    // for in
+    (
+        @for $target: tt $head: tt $kind: tt $pattern: tt
+        in $into_iterator: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        __py_comp_parse!(@bind $target $head $kind $pattern [$into_iterator] $($($rest)*)?)
+    };
+
+    // for in ... error
+    (@for $target: tt $head: tt $kind: tt $pattern: tt in $(; $($rest: tt)*)?) => {
+        __py_comp_error!(@at "expected an expression after `in`")
+    };
+
+    (@for $target: tt $head: tt $kind: tt $pattern: tt in $($rest: tt)*) => {
+        __py_comp_error!(@find "expected `;` after `in` expression" $($rest)*)
+    };
+
+    // for! in
+    ($target: tt [$head: tt] for ! $($rest: tt)*) => {
+        __py_comp_parse!(@pattern $target [$head] [expect] [] $($rest)*)
+    };
+
+    // for in
+    ($target: tt [$head: tt] for $($rest: tt)*) => {
+        __py_comp_parse!(@pattern $target [$head] [] [] $($rest)*)
+    };
+}
+
+/// Report a malformed comprehension.
+///
+/// `__py_comp_error!(@at "message" token)` reports the message followed by
+/// the offending token, as a single error. An error raised by a macro always
+/// points at the whole invocation, so the token is named in the message.
+/// The `@find` and `@yield` rules scan a clause or the yield expression for
+/// the first token which should have been preceded by a `;`, and the
+/// `@clause` rules report a clause which does not start with a keyword.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! __py_comp_error {
+    (@at $message: literal) => {
+        ::core::compile_error!($message)
+    };
+
+    (@at $message: literal $token: tt) => {
+        ::core::compile_error!(::core::concat!(
+            $message,
+            ", found `",
+            ::core::stringify!($token),
+            "`"
+        ))
+    };
+
+    // @find
+    (@find $message: literal) => {
+        __py_comp_error!(@at $message)
+    };
+
+    (@find $message: literal ; $($rest: tt)*) => {
+        __py_comp_error!(@at $message)
+    };
+
+    // The token is passed twice, so it can be matched against keywords while
+    // keeping the span of the copy it is reported at.
+    (@find $message: literal $token: tt $($rest: tt)*) => {
+        __py_comp_error!(@find_token $message $token [$token] $($rest)*)
+    };
+
+    (@find_token $message: literal , [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at "clauses must be separated by `;`" $token)
+    };
+
+    (@find_token $message: literal for [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at $message $token)
+    };
+
+    (@find_token $message: literal if [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at $message $token)
+    };
+
+    (@find_token $message: literal $other: tt [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@find $message $($rest)*)
+    };
+
+    // @clause
+    (@clause , [$token: tt]) => {
+        __py_comp_error!(@at "clauses must be separated by `;`" $token)
+    };
+
+    (@clause $other: tt [$token: tt]) => {
+        __py_comp_error!(@at
+            "expected a `for`, `if`, `if let`, `take`, `skip`, `step` or `scan` clause"
+            $token
+        )
+    };
+
+    // @for_pattern
+    // A `,` before the `in` is most likely a tuple pattern without parentheses.
+    (@for_pattern [$first: tt $($clause: tt)*] in $($rest: tt)*) => {
+        __py_comp_error!(@at "expected a single pattern between `for` and `in`" $first)
+    };
+
+    (@for_pattern $clause: tt ; $($rest: tt)*) => {
+        __py_comp_error!(@for_pattern $clause)
+    };
+
+    (@for_pattern [$first: tt $($clause: tt)*]) => {
+        __py_comp_error!(@at "expected `in` after the pattern of the `for` clause" $first)
+    };
+
+    (@for_pattern $clause: tt $token: tt $($rest: tt)*) => {
+        __py_comp_error!(@for_pattern_token $clause $token [$token] $($rest)*)
+    };
+
+    (@for_pattern_token $clause: tt , [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at
+            "the pattern of a `for` clause must be wrapped in parentheses to match a tuple"
+            $token
+        )
+    };
+
+    (@for_pattern_token $clause: tt $other: tt [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@for_pattern $clause $($rest)*)
+    };
+
+    // @yield
+    (@yield) => {
+        __py_comp_error!(@at "expected `;` and a `for` clause after the yield expression")
+    };
+
+    (@yield ;) => {
+        __py_comp_error!(@at "expected a `for` clause after the yield expression")
+    };
+
+    (@yield ; $token: tt $($rest: tt)*) => {
+        __py_comp_error!(@at
+            "comprehension must start with a `for` clause after the yield expression"
+            $token
+        )
+    };
+
+    (@yield $token: tt $($rest: tt)*) => {
+        __py_comp_error!(@yield_token $token [$token] $($rest)*)
+    };
+
+    (@yield_token , [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at "expected `;` after the yield expression" $token)
+    };
+
+    (@yield_token for [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@at "expected `;` after the yield expression" $token)
+    };
+
+    (@yield_token $other: tt [$token: tt] $($rest: tt)*) => {
+        __py_comp_error!(@yield $($rest)*)
+    };
+}
+
+/// Panic because an item did not match the pattern of a `for!` clause.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __py_comp_expect_failed {
+    ($( $pattern: pat )|+) => {
+        ::core::panic!(
+            "an item of a `for!` clause does not match the pattern `{}`",
+            ::core::stringify!($( $pattern )|+)
+        )
+    };
+}
+
+/// Expand the clauses of a comprehension to nested `for` loops.
+///
+/// This is the counterpart of the `@emit` and `@clauses` rules of [`comp!`]
+/// for the macros that do not produce an iterator. It is invoked as
+/// `__py_comp_parse!(loops [{ body }] for ...)`, and runs `body` for every
+/// item reaching the end of the clauses.
+#[doc(hidden)]
+#[macro_export(local_inner_macros)]
+macro_rules! __py_comp_loops {
+    // @clauses
+    // This case runs the body at the end of the final `for` clause.
+    (@clauses $body: block []) => {
+        $body
+    };
+
+    // @clauses for ...
+    // This case returns to the main macro parsing.
+    (@clauses $body: block [for $($rest: tt)*]) => {
+        __py_comp_parse!(loops [$body] for $($rest)*)
+    };
+
+    // @clauses if ...
+    (@clauses
+        $body: block $rest: tt
+        { if $condition: expr }
+        $($clause: tt)*
+    ) => {
+        if $condition {
+            __py_comp_loops!(@clauses $body $rest $($clause)*)
+        }
+    };
+
+    // @clauses if-let ...
+    (@clauses
+        $body: block $rest: tt
+        { if let $if_let_names: tt $( $if_let_pattern: pat )|+ = $if_let_expr: expr }
+        $($clause: tt)*
+    ) => {
+        if let $( $if_let_pattern )|+ = $if_let_expr {
+            __py_comp_loops!(@clauses $body $rest $($clause)*)
+        }
+    };
+
+    // @clauses match ...
+    (@clauses
+        $body: block $rest: tt
+        { match $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => __py_comp_loops!(@clauses $body $rest $($clause)*),
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+    };
+
+    // @clauses expect ...
+    (@clauses
+        $body: block $rest: tt
+        { expect $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => __py_comp_loops!(@clauses $body $rest $($clause)*),
+            #[allow(unreachable_patterns)]
+            _ => __py_comp_expect_failed!($( $pattern )|+),
+        }
+    };
+
+    // @clauses take ...
+    // The loop stops once the count reaches the limit, before pulling another
+    // item, so the count never exceeds it here.
+    (@clauses
+        $body: block $rest: tt
+        { take $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {{
+        $count += 1;
+        __py_comp_loops!(@clauses $body $rest $($clause)*)
+    }};
+
+    // @clauses skip ...
+    (@clauses
+        $body: block $rest: tt
+        { skip $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {
+        if $count < $limit {
+            $count += 1;
+        } else {
+            __py_comp_loops!(@clauses $body $rest $($clause)*)
+        }
+    };
+
+    // @clauses step ...
+    (@clauses
+        $body: block $rest: tt
+        { step $count: ident $limit: ident }
+        $($clause: tt)*
+    ) => {{
+        let due = $count == 0;
+        $count = ($count + 1) % $limit;
+        if due {
+            __py_comp_loops!(@clauses $body $rest $($clause)*)
+        }
+    }};
+
+    // @clauses scan ...
+    (@clauses
+        $body: block $rest: tt
+        { scan $state: ident $acc: ident = $update: expr }
+        $($clause: tt)*
+    ) => {{
+        let $acc = $state.take().unwrap();
+        let $acc = $update;
+        $state = Some(::core::clone::Clone::clone(&$acc));
+        __py_comp_loops!(@clauses $body $rest $($clause)*)
+    }};
+
+    // for in $( if | if-let | take | skip | step | scan )+ [for ...]
+    (@emit
+        [[$body: block] [$pattern: pat] [$into_iterator: expr] $($info: tt)*]
+        [$($state: tt)*] [$($clause: tt)*] [$({ $count: ident $limit: ident })+] $rest: tt
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        let mut iter = ::core::iter::IntoIterator::into_iter(into_iterator);
+        while !($($count == $limit)||+) {
+            match ::core::iter::Iterator::next(&mut iter) {
+                ::core::option::Option::Some($pattern) => {
+                    __py_comp_loops!(@clauses $body $rest $($clause)*)
+                }
+                ::core::option::Option::None => break,
+            }
+        }
+    }};
+
+    // for in $( if | if-let | skip | step | scan )* [for ...]
+    (@emit
+        [[$body: block] [$pattern: pat] [$into_iterator: expr] $($info: tt)*]
+        [$($state: tt)*] [$($clause: tt)*] $stop: tt $rest: tt
+    ) => {{
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $($state)*
+        for $pattern in into_iterator {
+            __py_comp_loops!(@clauses $body $rest $($clause)*)
+        }
+    }};
+}
+
+/// Describe how [`comp!`] expands a comprehension.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to a [`Plan`]
+/// listing every level of nesting with its clauses, the rule of [`comp!`]
+/// which expands it, the iterator adapters it expands to, and the closure
+/// passed to them. The plan is formatted as an indented tree, and may be
+/// converted to a Graphviz graph with [`Plan::to_dot`]. None of the
+/// expressions in the comprehension are evaluated.
+///
+/// [`Plan`]: explain/struct.Plan.html
+/// [`Plan::to_dot`]: explain/struct.Plan.html#method.to_dot
+///
+/// ```rust
+/// use py_comp::comp_explain;
+///
+/// let plan = comp_explain!((x, y); for x in 0..4; if x % 2 == 0; for y in 0..x);
+///
+/// assert_eq!(plan.levels.len(), 2);
+/// assert_eq!(plan.levels[0].pipeline, "into_iter().filter_map(..).flatten()");
+/// assert_eq!(plan.levels[1].pipeline, "into_iter().map(..)");
+///
+/// println!("{}", plan);
+/// println!("{}", plan.to_dot());
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_explain {
+    // @clauses
+    // The source text of the stateful clauses is found in the declarations of
+    // their state, which are in the same order as the clauses.
+    (@clauses [$($clause: tt)*] $state: tt []) => {
+        ::std::vec::Vec::from([$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*] $state: tt
+        [{ if let $if_let_names: tt $( $if_let_pattern: pat )|+ = $if_let_expr: expr } $($clause: tt)*]
+    ) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::IfLet,
+            source: ::core::stringify!(if let $( $if_let_pattern )|+ = $if_let_expr),
+        },] $state [$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*] $state: tt [{ if $condition: expr } $($clause: tt)*]) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::If,
+            source: ::core::stringify!(if $condition),
+        },] $state [$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*]
+        [let mut $count: ident: usize = 0; let $limit: ident: usize = $limit_expr: expr; $($state: tt)*]
+        [{ take $($ident: ident)* } $($clause: tt)*]
+    ) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::Take,
+            source: ::core::stringify!(take $limit_expr),
+        },] [$($state)*] [$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*]
+        [let mut $count: ident: usize = 0; let $limit: ident: usize = $limit_expr: expr; $($state: tt)*]
+        [{ skip $($ident: ident)* } $($clause: tt)*]
+    ) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::Skip,
+            source: ::core::stringify!(skip $limit_expr),
+        },] [$($state)*] [$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*]
+        [
+            let mut $count: ident: usize = 0;
+            let $limit: ident: usize = $limit_expr: expr;
+            ::core::assert!($($assert: tt)*);
+            $($state: tt)*
+        ]
+        [{ step $($ident: ident)* } $($clause: tt)*]
+    ) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::Step,
+            source: ::core::stringify!(step $limit_expr),
+        },] [$($state)*] [$($clause)*])
+    };
+
+    (@clauses [$($done: tt)*]
+        [let mut $scan_state: ident = Some($init: expr); $($state: tt)*]
+        [{ scan $state_ident: ident $acc: ident = $update: expr } $($clause: tt)*]
+    ) => {
+        comp_explain!(@clauses [$($done)* $crate::explain::Clause {
+            kind: $crate::explain::ClauseKind::Scan,
+            source: ::core::stringify!(scan $acc = $init => $update),
+        },] [$($state)*] [$($clause)*])
+    };
+
+    // @level
+    // The pattern of a `for` clause which may be refutable is matched by the
+    // first clause of its level, which is described as part of the `for`.
+    (@level $info: tt
+        [[$item_expr: expr] [$binding: pat] [$into_iterator: expr] $clause_source: tt $names: tt]
+        $state: tt [{ match $( $pattern: pat )|+ = $item: ident } $($clause: tt)*] $rest: tt
+    ) => {
+        comp_explain!(@describe $info
+            [$item_expr] [::core::stringify!(for $( $pattern )|+ in $into_iterator)] [$binding]
+            $state [$($clause)*] $rest
+        )
+    };
+
+    (@level $info: tt
+        [[$item_expr: expr] [$binding: pat] [$into_iterator: expr] $clause_source: tt $names: tt]
+        $state: tt [{ expect $( $pattern: pat )|+ = $item: ident } $($clause: tt)*] $rest: tt
+    ) => {
+        comp_explain!(@describe $info
+            [$item_expr]
+            [::core::concat!("for! ", ::core::stringify!($( $pattern )|+ in $into_iterator))]
+            [$binding]
+            $state [$($clause)*] $rest
+        )
+    };
+
+    (@level $info: tt
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] $names: tt]
+        $state: tt $clauses: tt $rest: tt
+    ) => {
+        comp_explain!(@describe $info
+            [$item_expr] [::core::stringify!(for $pattern in $into_iterator)] [$pattern]
+            $state $clauses $rest
+        )
+    };
+
+    (@describe
+        [$rule: literal $pipeline: literal $returns: literal]
+        [$item_expr: expr] [$source: expr] [$binding: pat]
+        $state: tt $clauses: tt [$($rest: tt)*]
+    ) => {{
+        let mut levels = ::std::vec::Vec::new();
+        levels.push($crate::explain::Level {
+            source: $source,
+            clauses: comp_explain!(@clauses [] $state $clauses),
+            rule: $rule,
+            pipeline: $pipeline,
+            closure: ::core::concat!("move |", ::core::stringify!($binding), "| -> ", $returns),
+        });
+        comp_explain!(@rest levels [$item_expr] $($rest)*);
+        levels
+    }};
+
+    (@rest $levels: ident [$item_expr: expr]) => {};
+
+    (@rest $levels: ident [$item_expr: expr] for $($rest: tt)*) => {
+        $levels.extend(__py_comp_parse!(explain [$item_expr] for $($rest)*))
+    };
+
+    // @emit
+    // These rules mirror the `@emit` rules of `comp!`.
+    (@emit $head: tt [] [] [] []) => {
+        comp_explain!(@level
+            ["for in" "into_iter().map(..)" "the item"]
+            $head [] [] []
+        )
+    };
+
+    (@emit $head: tt [] [] [] $rest: tt) => {
+        comp_explain!(@level
+            [
+                "for in for ..."
+                "Product"
+                "the next level"
+            ]
+            $head [] [] $rest
+        )
+    };
+
+    (@emit $head: tt $state: tt $clauses: tt [] []) => {
+        comp_explain!(@level
+            [
+                "for in $( if | if-let | skip | step | scan )+"
+                "into_iter().filter_map(..)"
+                "Some(the item) if it passes the clauses, or None"
+            ]
+            $head $state $clauses []
+        )
+    };
+
+    (@emit $head: tt $state: tt $clauses: tt [] $rest: tt) => {
+        comp_explain!(@level
+            [
+                "for in $( if | if-let | skip | step | scan )+ for ..."
+                "into_iter().filter_map(..).flatten()"
+                "Some(the next level) if it passes the clauses, or None"
+            ]
+            $head $state $clauses $rest
+        )
+    };
+
+    (@emit $head: tt $state: tt $clauses: tt $stop: tt []) => {
+        comp_explain!(@level
+            [
+                "for in $( if | if-let | take | skip | step | scan )+"
+                "until(into_iter(), ..).flatten()"
+                "(Some(the item) if it passes the clauses or None, whether a \
+                 take clause is done)"
+            ]
+            $head $state $clauses []
+        )
+    };
+
+    (@emit $head: tt $state: tt $clauses: tt $stop: tt $rest: tt) => {
+        comp_explain!(@level
+            [
+                "for in $( if | if-let | take | skip | step | scan )+ for ..."
+                "until(into_iter(), ..).flatten().flatten()"
+                "(Some(the next level) if it passes the clauses or None, whether \
+                 a take clause is done)"
+            ]
+            $head $state $clauses $rest
+        )
+    };
+
+    ($item_expr: expr; for $($rest: tt)*) => {
+        $crate::explain::Plan {
+            item: ::core::stringify!($item_expr),
+            levels: __py_comp_parse!(explain [$item_expr] for $($rest)*),
+        }
+    };
+}
+
+/// Run a block for every item of a comprehension, using nested `for` loops.
+///
+/// This accepts the clauses of [`comp!`] without the leading item expression,
+/// followed by `=>` and a block. The block runs inside the loop of the final
+/// `for` clause, and may use any name introduced by the clauses.
+///
+/// Since no closures are involved, the block may mutably borrow local
+/// variables, and the sources of nested levels may borrow any local variable.
+/// `return` returns from the enclosing function, and `break` and `continue`
+/// apply to the loop of the final `for` clause, or to any enclosing labeled
+/// loop or block:
+///
+/// ```rust
+/// use py_comp::comp_for_each;
+///
+/// let xs = vec![1, 2, 3, 4];
+/// let ys = vec![10, 20];
+/// let mut pairs = Vec::new();
+///
+/// 'search: {
+///     comp_for_each!(for x in &xs; if x % 2 == 0; for y in &ys => {
+///         if pairs.len() == 3 {
+///             break 'search;
+///         }
+///         pairs.push(x + y);
+///     });
+/// }
+///
+/// assert_eq!(pairs, vec![12, 22, 14]);
+/// ```
+///
+/// Note that very long invocations of this macro may require raising the
+/// `recursion_limit` of the crate, since the clauses are scanned one token at
+/// a time to find the `=>`.
+#[macro_export(local_inner_macros)]
+macro_rules! comp_for_each {
+    // @body =>
+    (@body [$($clauses: tt)*] => $body: block) => {
+        __py_comp_parse!(loops [$body] $($clauses)*)
+    };
+
+    // @body ...
+    // This case moves a single token of the clauses from the input to the
+    // accumulator, until the `=>` token is found.
+    (@body [$($clauses: tt)*] $token: tt $($rest: tt)*) => {
+        comp_for_each!(@body [$($clauses)* $token] $($rest)*)
+    };
+
+    (for $($rest: tt)*) => {
+        comp_for_each!(@body [] for $($rest)*)
+    };
+}
+
+/// A comprehension recording how many items every clause received and let
+/// through, and the time spent in it.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to a tuple of the
+/// comprehension and a [`Report`], which is updated as the comprehension is
+/// iterated. The report has an entry for every `for`, `if` and `if let`
+/// clause, labeled with the source text of the clause, and is formatted as a
+/// table. This helps to choose the order of the clauses, for instance to reject
+/// most items with the cheapest conditions first.
+///
+/// Counting and timing every clause makes the comprehension noticeably
+/// slower, so this is meant to be swapped in temporarily for [`comp!`]. A
+/// `for` clause lets through the items which match its pattern, after any
+/// modifier. Other clauses are not recorded.
+///
+/// [`Report`]: profile/struct.Report.html
+///
+/// ```rust
+/// use py_comp::comp_profiled;
+///
+/// let (items, report) = comp_profiled!(
+///     (x, y);
+///     for x in 0..10;
+///     if x % 3 == 0;
+///     for y in 0..x;
+///     if let 2..=4 = y;
+/// );
+/// assert_eq!(items.count(), 7);
+///
+/// let clauses = report.clauses();
+/// assert_eq!(clauses[1].label, "if x % 3 == 0");
+/// assert_eq!((clauses[1].received, clauses[1].emitted), (10, 4));
+/// assert_eq!((clauses[2].received, clauses[2].emitted), (4, 18));
+///
+/// println!("{}", report);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_profiled {
+    // @segment
+    // These rules split the clauses at every `;`.
+    (@segment $state: tt [$($segment: tt)*] ; $($rest: tt)*) => {
+        comp_profiled!(@clause $state [$($segment)*] [$($rest)*])
+    };
+
+    (@segment $state: tt [$($segment: tt)*]) => {
+        comp_profiled!(@clause $state [$($segment)*] [])
+    };
+
+    (@segment $state: tt [$($segment: tt)*] $token: tt $($rest: tt)*) => {
+        comp_profiled!(@segment $state [$($segment)* $token] $($rest)*)
+    };
+
+    // @clause for ...
+    (@clause $state: tt [for $($for: tt)*] $rest: tt) => {
+        comp_profiled!(@for_pattern $state [] [$($for)*] $rest)
+    };
+
+    (@for_pattern $state: tt [$($pattern: tt)*] [in $($source: tt)*] $rest: tt) => {
+        comp_profiled!(@for_source $state [$($pattern)*] [$($source)*] $rest)
+    };
+
+    (@for_pattern $state: tt [$($pattern: tt)*] [$token: tt $($for: tt)*] $rest: tt) => {
+        comp_profiled!(@for_pattern $state [$($pattern)* $token] [$($for)*] $rest)
+    };
+
+    // The modifiers are kept out of the recorded source.
+    (@for_source $state: tt $pattern: tt [fixed $($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [] [$($source)+] $rest)
+    };
+
+    (@for_source $state: tt $pattern: tt [pairwise $($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [pairwise] [$($source)+] $rest)
+    };
+
+    (@for_source $state: tt $pattern: tt [window $size: tt of $($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [window $size of] [$($source)+] $rest)
+    };
+
+    (@for_source $state: tt $pattern: tt [chunks $($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for_chunks $state $pattern [chunks] [$($source)+] $rest)
+    };
+
+    (@for_source $state: tt $pattern: tt [$($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [] [$($source)+] $rest)
+    };
+
+    (@for_chunks $state: tt $pattern: tt [$($modifier: tt)*] [of $($source: tt)+] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [$($modifier)* of] [$($source)+] $rest)
+    };
+
+    (@for_chunks $state: tt $pattern: tt [$($modifier: tt)*] [$token: tt $($source: tt)*] $rest: tt) => {
+        comp_profiled!(@for_chunks $state $pattern [$($modifier)* $token] [$($source)*] $rest)
+    };
+
+    // This case handles a source named `chunks` without a modifier.
+    (@for_chunks $state: tt $pattern: tt [$($source: tt)+] [] $rest: tt) => {
+        comp_profiled!(@for $state $pattern [] [$($source)+] $rest)
+    };
+
+    // The `scan` clause binds a clone of the report for the following clauses
+    // and nested levels, since the closure of every level needs its own. The
+    // `if` clause then counts the items matching the pattern, which may be
+    // refutable.
+    (@for
+        [$item: tt $top: tt [$report: ident] [$($label: tt)*] [$($one: tt)*] [$($clause: tt)*]]
+        [$($pattern: tt)*] [$($modifier: tt)*] [$($source: tt)+] $rest: tt
+    ) => {
+        comp_profiled!(@next
+            [$item $top [report]
+                [$($label)* [for $($pattern)* in $($modifier)* $($source)+]]
+                [$($one)* 1]
+                [$($clause)*
+                    for $($pattern)* in $($modifier)*
+                        $crate::profile::Report::__py_comp_source(
+                            &$report, 0 $(+ $one)*, || { $($source)+ }
+                        );
+                    scan report = ::core::clone::Clone::clone(&$report) => report;
+                    if $crate::profile::Report::__py_comp_matched(&report, 0 $(+ $one)*);
+                ]
+            ]
+            $rest
+        )
+    };
+
+    // @clause if let ...
+    (@clause $state: tt [if let $($if_let: tt)*] $rest: tt) => {
+        comp_profiled!(@if_let_pattern $state [] [$($if_let)*] $rest)
+    };
+
+    (@if_let_pattern
+        [$item: tt $top: tt [$report: ident] [$($label: tt)*] [$($one: tt)*] [$($clause: tt)*]]
+        [$($pattern: tt)*] [= $($if_let_expr: tt)+] $rest: tt
+    ) => {
+        comp_profiled!(@next
+            [$item $top [$report]
+                [$($label)* [if let $($pattern)* = $($if_let_expr)+]]
+                [$($one)* 1]
+                [$($clause)*
+                    if let $($pattern)* = $crate::profile::Report::__py_comp_value(
+                        &$report, 0 $(+ $one)*, || { $($if_let_expr)+ }
+                    );
+                    if $crate::profile::Report::__py_comp_matched(&$report, 0 $(+ $one)*);
+                ]
+            ]
+            $rest
+        )
+    };
+
+    (@if_let_pattern $state: tt [$($pattern: tt)*] [$token: tt $($if_let: tt)*] $rest: tt) => {
+        comp_profiled!(@if_let_pattern $state [$($pattern)* $token] [$($if_let)*] $rest)
+    };
+
+    // @clause if ...
+    (@clause
+        [$item: tt $top: tt [$report: ident] [$($label: tt)*] [$($one: tt)*] [$($clause: tt)*]]
+        [if $($condition: tt)+] $rest: tt
+    ) => {
+        comp_profiled!(@next
+            [$item $top [$report]
+                [$($label)* [if $($condition)+]]
+                [$($one)* 1]
+                [$($clause)*
+                    if $crate::profile::Report::__py_comp_condition(
+                        &$report, 0 $(+ $one)*, || { $($condition)+ }
+                    );
+                ]
+            ]
+            $rest
+        )
+    };
+
+    // @clause take | skip | step | scan ...
+    // These clauses are kept as they are.
+    (@clause
+        [$item: tt $top: tt $report: tt $labels: tt $ones: tt [$($clause: tt)*]]
+        [$($other: tt)*] $rest: tt
+    ) => {
+        comp_profiled!(@next [$item $top $report $labels $ones [$($clause)* $($other)*;]] $rest)
+    };
+
+    (@next $state: tt [$($rest: tt)+]) => {
+        comp_profiled!(@segment $state [] $($rest)+)
+    };
+
+    (@next
+        [[$item_expr: expr] [$top: ident] $report: tt [$([$($label: tt)*])*] $ones: tt [$($clause: tt)*]]
+        []
+    ) => {{
+        let $top = $crate::profile::Report::new(&[$(::core::stringify!($($label)*)),*]);
+        (comp!($item_expr; $($clause)*), $top)
+    }};
+
+    ($item_expr: expr; for $($rest: tt)*) => {
+        comp_profiled!(@segment [[$item_expr] [report] [report] [] [] []] [] for $($rest)*)
+    };
+}
+
+/// A comprehension which owns its sources, so it can be returned from a
+/// function.
+///
+/// This accepts the same syntax as [`comp!`], preceded by `move` and a list
+/// of local collections, which are moved into [`Shared`] pointers. Every
+/// level of the comprehension then holds its own share of them instead of
+/// borrowing them, and iterating over a `Shared` collection yields clones of
+/// its items. The comprehension is therefore `'static` as long as it does not
+/// borrow anything else, and `Send` when the listed collections are `Send`
+/// and `Sync`.
+///
+/// The listed collections may be named in any `in` clause, including the
+/// first one, and their methods may be called through `Deref` in any
+/// expression, but only a clone of their items may be yielded.
+///
+/// [`Shared`]: shared/struct.Shared.html
+///
+/// ```rust
+/// use py_comp::comp_owned;
+///
+/// fn pairs(n: usize) -> impl Iterator<Item = (String, String)> + Send + 'static {
+///     let names: Vec<String> = (0..n).map(|i| format!("n{}", i)).collect();
+///
+///     comp_owned!(
+///         move names;
+///         (a.clone(), b);
+///         for a in names;
+///         for b in names;
+///         if a < b;
+///     )
+/// }
+///
+/// let pairs: Vec<_> = pairs(3).collect();
+/// assert_eq!(pairs.len(), 3);
+/// assert_eq!(pairs[0], (String::from("n0"), String::from("n1")));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_owned {
+    // @pattern
+    // These rules find the source of the first `for` clause.
+    (@pattern $locals: tt $item: tt [$($pattern: tt)*] in $($rest: tt)*) => {
+        comp_owned!(@source $locals $item [$($pattern)*] [] $($rest)*)
+    };
+
+    (@pattern $locals: tt $item: tt [$($pattern: tt)*] $token: tt $($rest: tt)*) => {
+        comp_owned!(@pattern $locals $item [$($pattern)* $token] $($rest)*)
+    };
+
+    (@source $locals: tt $item: tt $pattern: tt [$($source: tt)*] $(; $($rest: tt)*)?) => {
+        comp_owned!(@modifier $locals $item $pattern [$($source)*] [$(; $($rest)*)?])
+    };
+
+    (@source $locals: tt $item: tt $pattern: tt [$($source: tt)*] $token: tt $($rest: tt)*) => {
+        comp_owned!(@source $locals $item $pattern [$($source)* $token] $($rest)*)
+    };
+
+    // @modifier
+    // The modifiers are kept out of the block cloning the shared sources.
+    (@modifier $locals: tt $item: tt $pattern: tt [fixed $($source: tt)+] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [fixed] [$($source)+] $rest)
+    };
+
+    (@modifier $locals: tt $item: tt $pattern: tt [pairwise $($source: tt)+] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [pairwise] [$($source)+] $rest)
+    };
+
+    (@modifier $locals: tt $item: tt $pattern: tt [window $size: tt of $($source: tt)+] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [window $size of] [$($source)+] $rest)
+    };
+
+    (@modifier $locals: tt $item: tt $pattern: tt [chunks $($source: tt)+] $rest: tt) => {
+        comp_owned!(@chunks $locals $item $pattern [chunks] [$($source)+] $rest)
+    };
+
+    (@modifier $locals: tt $item: tt $pattern: tt [$($source: tt)+] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [] [$($source)+] $rest)
+    };
+
+    (@chunks $locals: tt $item: tt $pattern: tt [$($modifier: tt)*] [of $($source: tt)+] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [$($modifier)* of] [$($source)+] $rest)
+    };
+
+    (@chunks $locals: tt $item: tt $pattern: tt [$($modifier: tt)*] [$token: tt $($source: tt)*] $rest: tt) => {
+        comp_owned!(@chunks $locals $item $pattern [$($modifier)* $token] [$($source)*] $rest)
+    };
+
+    // This case handles a source named `chunks` without a modifier.
+    (@chunks $locals: tt $item: tt $pattern: tt [$($source: tt)+] [] $rest: tt) => {
+        comp_owned!(@first $locals $item $pattern [] [$($source)+] $rest)
+    };
+
+    // @first
+    // The source of the first `for` clause is evaluated once, outside of the
+    // closures, so it clones the shared sources it moves.
+    (@first
+        [$($local: ident),+] $item: tt [$($pattern: tt)*] [$($modifier: tt)*] [$($source: tt)+]
+        [$($rest: tt)*]
+    ) => {
+        comp_owned!(@clauses [$($local),+] $item
+            [for $($pattern)* in $($modifier)* {
+                $(
+                    #[allow(unused_variables)]
+                    let $local = ::core::clone::Clone::clone(&$local);
+                )+
+                $($source)+
+            }]
+            $($rest)*
+        )
+    };
+
+    // @clauses
+    // A `scan` clause binds a clone of every shared source before every
+    // nested `for` clause, since the closure of every level needs its own.
+    (@clauses [$($local: ident),+] $item: tt [$($clause: tt)*] ; for $($rest: tt)*) => {
+        comp_owned!(@clauses [$($local),+] $item
+            [$($clause)* $(; scan $local = ::core::clone::Clone::clone(&$local) => $local)+ ; for]
+            $($rest)*
+        )
+    };
+
+    (@clauses $locals: tt $item: tt [$($clause: tt)*] $token: tt $($rest: tt)*) => {
+        comp_owned!(@clauses $locals $item [$($clause)* $token] $($rest)*)
+    };
+
+    (@clauses $locals: tt [$item_expr: expr] [$($clause: tt)*]) => {
+        comp!($item_expr; $($clause)*)
+    };
+
+    (move $($local: ident),+; $item_expr: expr; for $($rest: tt)*) => {{
+        $(let $local = $crate::shared::Shared::new($local);)+
+        comp_owned!(@pattern [$($local),+] [$item_expr] [] $($rest)*)
+    }};
+}
+
+/// A comprehension which can be iterated over any number of times.
+///
+/// This accepts the same syntax as [`comp!`], optionally preceded by `move`
+/// and a list of local collections like [`comp_owned!`], and evaluates to a
+/// [`Def`] holding the sources and clauses of the comprehension. Iterating
+/// over a reference to it builds a new iterator from the start every time,
+/// like calling a Python generator function rather than iterating over a
+/// generator object.
+///
+/// The sources are evaluated again for every iteration, so they must not move
+/// any local other than the listed ones, which are moved into [`Shared`]
+/// pointers and yield clones of their items.
+///
+/// [`Def`]: def/struct.Def.html
+/// [`Shared`]: shared/struct.Shared.html
+///
+/// ```rust
+/// use py_comp::comp_def;
+///
+/// let limits = vec![("cpu", 4), ("memory", 2)];
+///
+/// let slots = comp_def!(move limits; (name, slot); for (name, limit) in limits; for slot in 0..limit);
+///
+/// assert_eq!((&slots).into_iter().count(), 6);
+/// for (name, slot) in &slots {
+///     assert!(name == "cpu" || slot < 2);
+/// }
+/// assert_eq!(slots.iter().last(), Some(("memory", 1)));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_def {
+    (move $($local: ident),+; $item_expr: expr; for $($rest: tt)*) => {{
+        $(let $local = $crate::shared::Shared::new($local);)+
+        $crate::def::Def::new(move || {
+            $(let $local = ::core::clone::Clone::clone(&$local);)+
+            comp_owned!(@pattern [$($local),+] [$item_expr] [] $($rest)*)
+        })
+    }};
+
+    ($item_expr: expr; for $($rest: tt)*) => {
+        $crate::def::Def::new(move || comp!($item_expr; for $($rest)*))
+    };
+}
+
+/// A comprehension boxed as a `Box<dyn Iterator<Item = T> + 'a>`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to a [`Boxed`]
+/// iterator, so that comprehensions built by different branches of a function
+/// have the same type. The lifetime of the box is the shortest lifetime of the
+/// values the comprehension borrows.
+///
+/// When the clauses are preceded by `per level`, the comprehension nested in
+/// every `for` clause is boxed as well, instead of only the whole
+/// comprehension. Every level then yields a boxed iterator of the same type,
+/// which keeps the types of deeply nested comprehensions small, at the cost
+/// of an allocation for every item of every enclosing level.
+///
+/// [`Boxed`]: boxed/type.Boxed.html
+///
+/// ```rust
+/// use py_comp::{boxed::Boxed, comp_boxed};
+///
+/// fn cells<'a>(grid: &'a [Vec<u32>], by_column: bool) -> Boxed<'a, u32> {
+///     if by_column {
+///         let width = grid.first().map_or(0, Vec::len);
+///         comp_boxed!(per level row[column]; for column in 0..width; for row in grid)
+///     } else {
+///         comp_boxed!(*cell; for row in grid; for cell in row)
+///     }
+/// }
+///
+/// let grid = vec![vec![1, 2], vec![3, 4]];
+/// assert_eq!(cells(&grid, false).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+/// assert_eq!(cells(&grid, true).collect::<Vec<_>>(), vec![1, 3, 2, 4]);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_boxed {
+    // @level
+    // These rules split the clauses before every nested `for` clause, and
+    // move the nested levels into a boxed comprehension iterated over by the
+    // final `for` clause of the enclosing level.
+    (@level [$($box: tt)*] $item: tt [$($clause: tt)*] ; for $($rest: tt)*) => {
+        $($box)*(comp!(
+            item;
+            $($clause)*;
+            for item in comp_boxed!(@level [$($box)*] $item [] for $($rest)*)
+        ))
+    };
+
+    (@level $box: tt $item: tt [$($clause: tt)*] $token: tt $($rest: tt)*) => {
+        comp_boxed!(@level $box $item [$($clause)* $token] $($rest)*)
+    };
+
+    (@level [$($box: tt)*] [$item_expr: expr] [$($clause: tt)*]) => {
+        $($box)*(comp!($item_expr; $($clause)*))
+    };
+
+    (@per_level $box: tt $item_expr: expr; for $($rest: tt)*) => {
+        comp_boxed!(@level $box [$item_expr] [] for $($rest)*)
+    };
+
+    (per level $($rest: tt)*) => {
+        comp_boxed!(@per_level [$crate::boxed::boxed] $($rest)*)
+    };
+
+    ($($rest: tt)*) => {
+        $crate::boxed::boxed(comp!($($rest)*))
+    };
+}
+
+/// A comprehension boxed as a `Box<dyn Iterator<Item = T> + Send + 'a>`.
+///
+/// This is the same as [`comp_boxed!`], including the `per level` prefix, but
+/// evaluates to a [`BoxedSend`] iterator, which can be sent to another thread
+/// when the sources and the values captured by the comprehension are `Send`.
+///
+/// [`BoxedSend`]: boxed/type.BoxedSend.html
+///
+/// ```rust
+/// use py_comp::{boxed::BoxedSend, comp_dyn};
+///
+/// fn multiples(limit: u64, odd: bool) -> BoxedSend<'static, u64> {
+///     if odd {
+///         comp_dyn!(per level x * k; for x in (1..limit).step_by(2); for k in 1..=2)
+///     } else {
+///         comp_dyn!(x * 2; for x in 1..limit)
+///     }
+/// }
+///
+/// let handle = std::thread::spawn(|| multiples(4, true).sum::<u64>());
+/// assert_eq!(handle.join().unwrap(), 12);
+/// assert_eq!(multiples(4, false).collect::<Vec<_>>(), vec![2, 4, 6]);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_dyn {
+    (per level $($rest: tt)*) => {
+        comp_boxed!(@per_level [$crate::boxed::boxed_send] $($rest)*)
+    };
+
+    ($($rest: tt)*) => {
+        $crate::boxed::boxed_send(comp!($($rest)*))
+    };
+}
+
+/// Define a named iterator type for a comprehension.
+///
+/// The type can be named in struct fields, associated types and function
+/// signatures without boxing it, unlike the closures [`comp!`] expands to.
+/// The definition lists the inputs of the comprehension, which become the
+/// arguments of the `new` function of the type, and the type of its items,
+/// followed by the comprehension itself. The type may be generic over
+/// lifetimes, but not over types.
+///
+/// Every `for` clause must be followed by `=>` and the type of its source,
+/// since the type holds an iterator over every source. Likewise, an `if let`
+/// clause of every `for` clause but the final one must be followed by `=>`
+/// and the type of the value it matches, which the type holds while the
+/// nested levels run. Only `for`, `if` and `if let` clauses are accepted.
+///
+/// The inputs are cloned whenever the clauses are evaluated for an item, and
+/// the items of all but the final `for` clause, along with the values matched
+/// by their `if let` clauses, are cloned whenever their bindings are needed,
+/// so these should be cheap to clone, such as references, like the values
+/// captured by [`comp!`] must be `Copy`. Every expression is still evaluated
+/// only once per item.
+///
+/// ```rust
+/// use py_comp::comp_struct;
+///
+/// comp_struct! {
+///     /// The pairs of an item of `xs` and a larger item of `ys`.
+///     #[derive(Clone, Debug)]
+///     pub struct Increasing<'a>(xs: &'a [u32], ys: &'a [u32]) -> (u32, u32) {
+///         (*x, *y);
+///         for x in xs => &'a [u32];
+///         for y in ys => &'a [u32];
+///         if x < y;
+///     }
+/// }
+///
+/// struct Schedule<'a> {
+///     slots: Increasing<'a>,
+/// }
+///
+/// let schedule = Schedule { slots: Increasing::new(&[1, 2, 3], &[2, 3]) };
+/// assert_eq!(schedule.slots.collect::<Vec<_>>(), vec![(1, 2), (1, 3), (2, 3)]);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_struct {
+    // @parse
+    // These rules collect the clauses of every `for` clause into a level
+    // `{[pattern] [source] [type] [clauses]}`.
+    (@parse $header: tt [] {}
+        for $pattern: pat in $source: expr => $source_ty: ty
+        $(; $($rest: tt)*)?
+    ) => {
+        comp_struct!(@parse $header [] {[$pattern] [$source] [$source_ty] []} $($($rest)*)?);
+    };
+
+    (@parse $header: tt $levels: tt {$pattern: tt $source: tt $source_ty: tt $clauses: tt}
+        for $($rest: tt)*
+    ) => {
+        comp_struct!(@enclosing $clauses $header $levels {$pattern $source $source_ty $clauses}
+            for $($rest)*
+        );
+    };
+
+    (@parse $header: tt $levels: tt {$pattern: tt $source: tt $source_ty: tt [$($clause: tt)*]}
+        if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr $(=> $matched_ty: ty)?
+        $(; $($rest: tt)*)?
+    ) => {
+        comp_struct!(@parse $header $levels
+            {$pattern $source $source_ty [
+                $($clause)*
+                { if let [$( $if_let_pattern )|+] [$if_let_expr] [$($matched_ty)?] }
+            ]}
+            $($($rest)*)?
+        );
+    };
+
+    (@parse $header: tt $levels: tt {$pattern: tt $source: tt $source_ty: tt [$($clause: tt)*]}
+        if $condition: expr
+        $(; $($rest: tt)*)?
+    ) => {
+        comp_struct!(@parse $header $levels
+            {$pattern $source $source_ty [$($clause)* { if $condition }]}
+            $($($rest)*)?
+        );
+    };
+
+    (@parse $header: tt [$($level: tt)*] $pending: tt) => {
+        comp_struct!(@emit $header [$($level)* $pending]);
+    };
+
+    // @enclosing
+    // A level followed by another `for` clause holds the values matched by
+    // its `if let` clauses, so their types must be given.
+    (@enclosing [] $header: tt [$($level: tt)*] $pending: tt
+        for $pattern: pat in $source: expr => $source_ty: ty
+        $(; $($rest: tt)*)?
+    ) => {
+        comp_struct!(@parse $header [$($level)* $pending]
+            {[$pattern] [$source] [$source_ty] []}
+            $($($rest)*)?
+        );
+    };
+
+    (@enclosing [{ if let $patterns: tt [$if_let_expr: expr] [] } $($clause: tt)*] $($rest: tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "expected `=>` and the type of the matched value after `",
+            ::core::stringify!($if_let_expr),
+            "`, since the `if let` clause is followed by another `for` clause"
+        ));
+    };
+
+    (@enclosing [$clause: tt $($clauses: tt)*] $($rest: tt)*) => {
+        comp_struct!(@enclosing [$($clauses)*] $($rest)*);
+    };
+
+    (@enclosing [] $header: tt $levels: tt $pending: tt for $pattern: pat in $($rest: tt)*) => {
+        const _: () = __py_comp_error!(@find
+            "expected `=>` and the type of the source after the `in` expression"
+            $($rest)*
+        );
+    };
+
+    (@parse $header: tt $levels: tt $pending: tt for $pattern: pat in $($rest: tt)*) => {
+        const _: () = __py_comp_error!(@find
+            "expected `=>` and the type of the source after the `in` expression"
+            $($rest)*
+        );
+    };
+
+    (@parse $header: tt $levels: tt $pending: tt $token: tt $($rest: tt)*) => {
+        const _: () = __py_comp_error!(@at "expected a `for`, `if` or `if let` clause" $token);
+    };
+
+    // @emit
+    (@emit
+        [
+            [$(#[$attr: meta])*] [$vis: vis] [$name: ident] [$($lifetime: lifetime),*]
+            [$($input: ident: $input_ty: ty),*] [$item_ty: ty] [$item_expr: expr]
+        ]
+        [{[$pattern: pat] [$source: expr] $source_ty: tt $clauses: tt} $($level: tt)*]
+    ) => {
+        $(#[$attr])*
+        $vis struct $name<$($lifetime),*> {
+            $($input: $input_ty,)*
+            __py_comp_levels: comp_struct!(@type {[$pattern] [$source] $source_ty $clauses} $($level)*),
+        }
+
+        impl<$($lifetime),*> $name<$($lifetime),*> {
+            /// Create an iterator over the items of the comprehension.
+            #[allow(clippy::too_many_arguments)]
+            $vis fn new($($input: $input_ty),*) -> Self {
+                let mut this = $name {
+                    $($input,)*
+                    __py_comp_levels: ::core::default::Default::default(),
+                };
+                this.__py_comp_levels.iter = ::core::option::Option::Some(
+                    comp_struct!(@bind this [$($input),*] [] {
+                        ::core::iter::IntoIterator::into_iter($source)
+                    })
+                );
+                this
+            }
+        }
+
+        impl<$($lifetime),*> ::core::iter::Iterator for $name<$($lifetime),*> {
+            type Item = $item_ty;
+
+            fn next(&mut self) -> ::core::option::Option<$item_ty> {
+                comp_struct!(@next self [$($input),*] [$item_expr] [] [.__py_comp_levels]
+                    {[$pattern] [$source] $source_ty $clauses} $($level)*
+                )
+            }
+        }
+    };
+
+    // @type
+    (@type {$pattern: tt $source: tt [$source_ty: ty] $clauses: tt}) => {
+        $crate::named::Last<<$source_ty as ::core::iter::IntoIterator>::IntoIter>
+    };
 
-use doc_comment::doctest;
+    (@type {$pattern: tt $source: tt [$source_ty: ty] [$($clause: tt)*]} $($level: tt)+) => {
+        $crate::named::Level<
+            <$source_ty as ::core::iter::IntoIterator>::IntoIter,
+            comp_struct!(@matched $($clause)*),
+            comp_struct!(@type $($level)+),
+        >
+    };
 
-doctest!("../Readme.md");
+    // @matched
+    // The values matched by the `if let` clauses of a level are nested in
+    // pairs, ending with `()`.
+    (@matched) => {
+        ()
+    };
 
-/// Check that the type of the expression passed here implements IntoIterator.
-#[doc(hidden)]
-#[inline(always)]
-pub fn __py_comp_assert_impl_into_iter<T: IntoIterator>(_: &T) {}
+    (@matched { if let $patterns: tt $if_let_expr: tt [$matched_ty: ty] } $($clause: tt)*) => {
+        ($matched_ty, comp_struct!(@matched $($clause)*))
+    };
 
-/// A Python-like lazy generator-expression
-///
-/// For details see [module level documentation][super]
-///
-/// [super]: ../py_comp/index.html
-#[macro_export(local_inner_macros)]
-macro_rules! comp {
-    // @parse_if if
-    (@parse_if
-        $item_expr: expr;
-        if $condition: expr
+    (@matched { if $condition: expr } $($clause: tt)*) => {
+        comp_struct!(@matched $($clause)*)
+    };
+
+    // @next
+    // The enclosing levels are collected innermost first, along with the
+    // path to their state, and the final level advances them when its source
+    // runs out.
+    (@next $this: tt $inputs: tt [$item_expr: expr] $outer: tt [$($path: tt)*]
+        {[$pattern: pat] [$source: expr] $source_ty: tt [$($clause: tt)*]}
     ) => {
-        if $condition {
-            Some($item_expr)
-        } else {
-            None
+        loop {
+            match &mut $this $($path)* .iter {
+                ::core::option::Option::Some(iter) => match ::core::iter::Iterator::next(iter) {
+                    ::core::option::Option::Some(item) => comp_struct!(@bind $this $inputs $outer {
+                        let $pattern = item;
+                        comp_struct!(@filter [$($clause)*] {
+                            return ::core::option::Option::Some($item_expr);
+                        })
+                    }),
+                    ::core::option::Option::None => $this $($path)* .iter = ::core::option::Option::None,
+                },
+                ::core::option::Option::None => {
+                    comp_struct!(@refill $this $inputs $outer [$($path)*] [$source] { return ::core::option::Option::None; });
+                }
+            }
         }
     };
 
-    // @parse_if if-let
-    (@parse_if
-        $item_expr: expr;
-        if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr
+    (@next $this: tt $inputs: tt $item: tt [$($outer: tt)*] [$($path: tt)*]
+        {$pattern: tt $source: tt $source_ty: tt $clauses: tt}
+        $($level: tt)+
     ) => {
-        if let $( $if_let_pattern )|+ = $if_let_expr {
-            Some($item_expr)
-        } else {
-            None
+        comp_struct!(@next $this $inputs $item
+            [{[$($path)*] $pattern $source $clauses} $($outer)*]
+            [$($path)* .inner]
+            $($level)+
+        )
+    };
+
+    // @advance
+    // This evaluates to whether an enclosing level found an item, which it
+    // stores for the levels nested in it.
+    (@advance $this: tt $inputs: tt
+        {[$($path: tt)*] [$pattern: pat] [$source: expr] [$($clause: tt)*]}
+        $outer: tt
+    ) => {
+        loop {
+            match &mut $this $($path)* .iter {
+                ::core::option::Option::Some(iter) => match ::core::iter::Iterator::next(iter) {
+                    ::core::option::Option::Some(item) => comp_struct!(@bind $this $inputs $outer {
+                        #[allow(unused_variables)]
+                        let $pattern = ::core::clone::Clone::clone(&item);
+                        comp_struct!(@keep [$($clause)*] [] [$this [$($path)*] item])
+                    }),
+                    ::core::option::Option::None => $this $($path)* .iter = ::core::option::Option::None,
+                },
+                ::core::option::Option::None => {
+                    comp_struct!(@refill $this $inputs $outer [$($path)*] [$source] { break false; });
+                }
+            }
         }
     };
 
-    // @parse_if if for ...
-    // This case returns to the main macro parsing.
-    (@parse_if
-        $item_expr: expr;
-        if $condition: expr;
-        for $($rest: tt)*
+    // @refill
+    // The source of the first level is only evaluated by `new`.
+    (@refill $this: tt $inputs: tt [] $path: tt $source: tt { $($exhausted: tt)* }) => {
+        $($exhausted)*
+    };
+
+    (@refill $this: tt $inputs: tt [$next: tt $($outer: tt)*] [$($path: tt)*] [$source: expr]
+        { $($exhausted: tt)* }
     ) => {
+        if !comp_struct!(@advance $this $inputs $next [$($outer)*]) {
+            $($exhausted)*
+        }
+        $this $($path)* .iter = ::core::option::Option::Some(
+            comp_struct!(@bind $this $inputs [$next $($outer)*] {
+                ::core::iter::IntoIterator::into_iter($source)
+            })
+        );
+    };
+
+    // @keep
+    // This filters the item of an enclosing level like `@filter`, and stores
+    // it along with the values matched by its `if let` clauses, which are
+    // collected as the names they are bound to, before breaking out of
+    // `@advance`.
+    (@keep [] [$($matched: ident)*] [$this: tt [$($path: tt)*] $item: ident]) => {{
+        $this $($path)* .item = ::core::option::Option::Some(
+            ($item, comp_struct!(@nest $($matched)*))
+        );
+        break true;
+    }};
+
+    (@keep
+        [{ if let [$( $if_let_pattern: pat )|+] [$if_let_expr: expr] [$($matched_ty: ty)?] } $($clause: tt)*]
+        [$($matched: ident)*] $level: tt
+    ) => {{
+        let matched $(: $matched_ty)? = $if_let_expr;
+        match ::core::clone::Clone::clone(&matched) {
+            #[allow(unused_variables)]
+            $( $if_let_pattern )|+ => comp_struct!(@keep [$($clause)*] [$($matched)* matched] $level),
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+    }};
+
+    (@keep [{ if $condition: expr } $($clause: tt)*] $matched: tt $level: tt) => {
         if $condition {
-            Some(comp!($item_expr; for $($rest)*))
-        } else {
-            None
+            comp_struct!(@keep [$($clause)*] $matched $level)
         }
     };
 
-    // @parse_if if-let for ...
-    // This case returns to the main macro parsing.
-    (@parse_if
-        $item_expr: expr;
-        if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr;
-        for $($rest: tt)*
+    (@nest) => {
+        ()
+    };
+
+    (@nest $matched: ident $($rest: ident)*) => {
+        ($matched, comp_struct!(@nest $($rest)*))
+    };
+
+    // @bind
+    // This binds the inputs and the items of the enclosing levels, outermost
+    // first, around the body.
+    (@bind $this: tt [$($input: ident),*] [] { $($body: tt)* }) => {{
+        $(
+            #[allow(unused_variables)]
+            let $input = ::core::clone::Clone::clone(&$this.$input);
+        )*
+        $($body)*
+    }};
+
+    (@bind $this: tt $inputs: tt
+        [{[$($path: tt)*] [$pattern: pat] $source: tt [$($clause: tt)*]} $($outer: tt)*]
+        { $($body: tt)* }
+    ) => {
+        comp_struct!(@bind $this $inputs [$($outer)*] {
+            let (item, matched) = ::core::clone::Clone::clone(
+                ::core::option::Option::as_ref(&$this $($path)* .item).unwrap()
+            );
+            #[allow(unused_variables)]
+            let $pattern = item;
+            comp_struct!(@rebind matched $($clause)*);
+            $($body)*
+        })
+    };
+
+    // @rebind
+    // The `if let` clauses of an enclosing level matched the values stored
+    // with its current item, so their patterns are bound from clones of them.
+    (@rebind $matched: ident) => {};
+
+    (@rebind $matched: ident
+        { if let [$( $if_let_pattern: pat )|+] $if_let_expr: tt $matched_ty: tt }
+        $($clause: tt)*
+    ) => {
+        let (value, rest) = $matched;
+        #[allow(unused_variables)]
+        let $( $if_let_pattern )|+ = value else {
+            ::core::unreachable!()
+        };
+        comp_struct!(@rebind rest $($clause)*);
+    };
+
+    (@rebind $matched: ident { if $condition: expr } $($clause: tt)*) => {
+        comp_struct!(@rebind $matched $($clause)*);
+    };
+
+    // @filter
+    (@filter [] { $($body: tt)* }) => {
+        { $($body)* }
+    };
+
+    (@filter
+        [{ if let [$( $if_let_pattern: pat )|+] [$if_let_expr: expr] $matched_ty: tt } $($clause: tt)*]
+        $body: tt
     ) => {
         if let $( $if_let_pattern )|+ = $if_let_expr {
-            Some(comp!($item_expr; for $($rest)*))
-        } else {
-            None
+            comp_struct!(@filter [$($clause)*] $body)
         }
     };
 
-    // @parse_if if if ...
-    (@parse_if
-        $item_expr: expr;
-        if $condition: expr;
-        if $($rest: tt)*
+    (@filter [{ if $condition: expr } $($clause: tt)*] $body: tt) => {
+        if $condition {
+            comp_struct!(@filter [$($clause)*] $body)
+        }
+    };
+
+    (
+        $(#[$attr: meta])*
+        $vis: vis struct $name: ident $(<$($lifetime: lifetime),* $(,)?>)?
+        ($($input: ident: $input_ty: ty),* $(,)?) -> $item_ty: ty
+        { $item_expr: expr; for $($rest: tt)* }
+    ) => {
+        comp_struct!(@parse
+            [
+                [$(#[$attr])*] [$vis] [$name] [$($($lifetime),*)?]
+                [$($input: $input_ty),*] [$item_ty] [$item_expr]
+            ]
+            [] {} for $($rest)*
+        );
+    };
+}
+
+/// A comprehension which can be stopped and resumed from a checkpoint.
+///
+/// This accepts the same syntax as [`comp!`], without the `take`, `skip`,
+/// `step` and `scan` clauses, whose state could not be restored. It evaluates
+/// to a [`Resumable`] iterator, whose [`cursor`][Resumable::cursor] method
+/// returns its position as a [`Cursor`] holding one index per `for` clause.
+/// The cursor can be formatted and parsed, and passed to the
+/// [`resume`][Resumable::resume] method of the same comprehension built again
+/// later, to continue where the first one stopped.
+///
+/// The sources must be re-iterable, such as slices, ranges and clones of an
+/// iterator, so that they yield the same items when built again.
+///
+/// [`Resumable`]: cursor/struct.Resumable.html
+/// [Resumable::cursor]: cursor/struct.Resumable.html#method.cursor
+/// [Resumable::resume]: cursor/struct.Resumable.html#method.resume
+/// [`Cursor`]: cursor/struct.Cursor.html
+///
+/// ```rust
+/// use py_comp::{comp_resumable, cursor::Cursor};
+///
+/// let words = &["ab", "cd"];
+///
+/// let mut letters = comp_resumable!(c; for word in words; for c in word.chars());
+/// assert_eq!(letters.next(), Some('a'));
+/// assert_eq!(letters.next(), Some('b'));
+/// assert_eq!(letters.next(), Some('c'));
+///
+/// let checkpoint = letters.cursor().to_string();
+/// assert_eq!(checkpoint, "2,1");
+///
+/// let cursor: Cursor = checkpoint.parse().unwrap();
+/// let resumed = comp_resumable!(c; for word in words; for c in word.chars()).resume(&cursor);
+/// assert_eq!(resumed.collect::<String>(), "d");
+/// ```
+///
+/// Clauses which keep a state between items are rejected:
+///
+/// ```compile_fail
+/// use py_comp::comp_resumable;
+///
+/// let _ = comp_resumable!(x; for x in 0..10; take 3);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_resumable {
+    // @clauses
+    (@clauses [$item_expr: expr] []) => {
+        Some($item_expr)
+    };
+
+    // @clauses for ...
+    (@clauses [$item_expr: expr] [for $($rest: tt)*]) => {
+        Some(__py_comp_parse!(resumable [$item_expr] for $($rest)*))
+    };
+
+    // @clauses if ...
+    (@clauses
+        [$item_expr: expr] $rest: tt
+        { if $condition: expr }
+        $($clause: tt)*
     ) => {
         if $condition {
-            comp!(@parse_if $item_expr; if $($rest)*)
+            comp_resumable!(@clauses [$item_expr] $rest $($clause)*)
         } else {
             None
         }
     };
 
-    // @parse_if if-let if ...
-    (@parse_if
-        $item_expr: expr;
-        if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr;
-        if $($rest: tt)*
+    // @clauses if-let ...
+    (@clauses
+        [$item_expr: expr] $rest: tt
+        { if let $if_let_names: tt $( $if_let_pattern: pat )|+ = $if_let_expr: expr }
+        $($clause: tt)*
     ) => {
         if let $( $if_let_pattern )|+ = $if_let_expr {
-            comp!(@parse_if $item_expr; if $($rest)*)
+            comp_resumable!(@clauses [$item_expr] $rest $($clause)*)
         } else {
             None
         }
     };
 
-    // for in
-    (
-        $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr $(;)?
-    ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .map(move |$pattern| $item_expr)
-    }};
+    // @clauses match ...
+    (@clauses
+        [$item_expr: expr] $rest: tt
+        { match $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => comp_resumable!(@clauses [$item_expr] $rest $($clause)*),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    };
 
-    // for in $( if $( if-let )* )+
-    (
-        $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr
-        $(
-            ; if $condition: expr
-            $( ; if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr )*
-        )+
-        $(;)?
+    // @clauses expect ...
+    (@clauses
+        [$item_expr: expr] $rest: tt
+        { expect $( $pattern: pat )|+ = $item: ident }
+        $($clause: tt)*
+    ) => {
+        match $item {
+            $( $pattern )|+ => comp_resumable!(@clauses [$item_expr] $rest $($clause)*),
+            #[allow(unreachable_patterns)]
+            _ => __py_comp_expect_failed!($( $pattern )|+),
+        }
+    };
+
+    // @clauses take | skip | step | scan ...
+    (@clauses [$item_expr: expr] $rest: tt { $kind: ident $($clause: tt)* } $($clauses: tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "`",
+            ::core::stringify!($kind),
+            "` clauses are not supported by `comp_resumable!`"
+        ))
+    };
+
+    // for in $( if | if-let )*
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] $($info: tt)*]
+        [$($clause: tt)*] []
     ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .filter_map(move |$pattern|
-                comp!(@parse_if
-                    $item_expr
-                    $(
-                        ; if $condition
-                        $( ; if let $( $if_let_pattern )|+ = $if_let_expr )*
-                    )+
-                )
-            )
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $crate::cursor::last(
+            ::core::iter::IntoIterator::into_iter(into_iterator),
+            move |$pattern| comp_resumable!(@clauses [$item_expr] [] $($clause)*),
+        )
     }};
 
-    // for in $( if-let $( if )* )+
-    (
-        $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr
-        $(
-            ; if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr
-            $( ; if $condition: expr )*
-        )+
-        $(;)?
+    // for in $( if | if-let )* for ...
+    (@emit
+        [[$item_expr: expr] [$pattern: pat] [$into_iterator: expr] $($info: tt)*]
+        [$($clause: tt)*] $rest: tt
     ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .filter_map(move |$pattern|
-                comp!(@parse_if
-                    $item_expr
-                    $(
-                        ; if let $( $if_let_pattern )|+ = $if_let_expr
-                        $( ; if $condition )*
-                    )+
-                )
-            )
+        let source = $crate::__PyCompSource { into_iterator: $into_iterator };
+        #[allow(unused_imports)]
+        use $crate::{__PyCompIntoIter as _, __PyCompIntoNothing as _};
+        let into_iterator = $crate::__PyCompChecked(source.into_iterator).__py_comp_into_iter();
+        $crate::cursor::nested(
+            ::core::iter::IntoIterator::into_iter(into_iterator),
+            move |$pattern| comp_resumable!(@clauses [$item_expr] $rest $($clause)*),
+        )
     }};
 
-    // for in for ...
-    (
-        $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr;
-        for $($rest: tt)*
-    ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .flat_map(move |$pattern|
-                comp!($item_expr; for $($rest)*)
-            )
+    ($item_expr: expr; for $($rest: tt)*) => {
+        $crate::cursor::Resumable::new(
+            __py_comp_parse!(resumable [$item_expr] for $($rest)*)
+        )
+    };
+}
+
+/// A random-access comprehension over the cartesian product of slices and
+/// ranges.
+///
+/// This accepts the same syntax as [`comp!`], limited to `for` clauses whose
+/// sources implement [`RandomAccess`], such as references to slices, arrays
+/// and vectors, and ranges of integers. The sources are evaluated once, before
+/// any item, so they may not use the names introduced by earlier clauses.
+///
+/// This evaluates to an [`Indexed`] iterator, which computes any item from its
+/// position in constant time. Besides iterating over it, you may skip to any
+/// item with `nth`, access items with [`get`][Indexed::get], and split it into
+/// independent iterators with [`split_at`][Indexed::split_at], for instance to
+/// enumerate its halves in parallel.
+///
+/// [`RandomAccess`]: indexed/trait.RandomAccess.html
+/// [`Indexed`]: indexed/struct.Indexed.html
+/// [Indexed::get]: indexed/struct.Indexed.html#method.get
+/// [Indexed::split_at]: indexed/struct.Indexed.html#method.split_at
+///
+/// ```rust
+/// use py_comp::comp_indexed;
+///
+/// let digits = &['0', '1'];
+///
+/// let mut codes = comp_indexed!(
+///     format!("{}{}{}", a, b, c);
+///     for a in digits;
+///     for b in digits;
+///     for c in digits;
+/// );
+///
+/// assert_eq!(codes.len(), 8);
+/// assert_eq!(codes.get(5), Some(String::from("101")));
+/// assert_eq!(codes.nth(6), Some(String::from("110")));
+///
+/// let (low, high) = comp_indexed!((x, y); for x in 0..3; for y in 0..2).split_at(3);
+///
+/// assert_eq!(low.collect::<Vec<_>>(), vec![(0, 0), (0, 1), (1, 0)]);
+/// assert_eq!(high.collect::<Vec<_>>(), vec![(1, 1), (2, 0), (2, 1)]);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_indexed {
+    ($item_expr: expr; $(for $pattern: pat in $source: expr);+ $(;)?) => {
+        $crate::indexed::Indexed::new(
+            ($($source,)+),
+            move |($($pattern,)+)| $item_expr,
+        )
+    };
+}
+
+/// Append the items of a comprehension to an existing collection.
+///
+/// This accepts the same syntax as [`comp!`], preceded by the target
+/// collection and a semicolon. The target is a place expression, such as a
+/// local variable or a field, and is borrowed mutably by the macro. It must
+/// implement [`Extend`] for the items, and [`Reserve`], which is implemented
+/// for the collections of the standard library.
+///
+/// Before extending the target, the macro reserves capacity for the lower
+/// bound of the comprehension's `size_hint`, and then passes the lazy
+/// iterator itself to [`Extend::extend`], so no intermediate collection is
+/// allocated. The lower bound is exact whenever the comprehension only has
+/// `for` clauses over exact-size sources, all of them but the first marked
+/// `fixed` or written with literals only. Otherwise the nested sources are
+/// not counted before they are iterated, so a nested source which is a plain
+/// name should be marked `fixed` for its items to be reserved.
+///
+/// [`Reserve`]: extend/trait.Reserve.html
+///
+/// ```rust
+/// use py_comp::comp_extend;
+/// use std::collections::HashMap;
+///
+/// let mut squares = vec![0];
+/// comp_extend!(squares; x * x; for x in 1..4);
+///
+/// let mut lengths = HashMap::new();
+/// comp_extend!(lengths; (*word, word.len()); for word in &["a", "bb"]);
+///
+/// assert_eq!(squares, vec![0, 1, 4, 9]);
+/// assert_eq!(lengths["bb"], 2);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_extend {
+    ($target: expr; $item_expr: expr; for $($rest: tt)*) => {{
+        let target = &mut $target;
+        let items = comp!($item_expr; for $($rest)*);
+        $crate::extend::Reserve::reserve(
+            target,
+            ::core::iter::Iterator::size_hint(&items).0,
+        );
+        ::core::iter::Extend::extend(target, items);
     }};
+}
 
-    // for in $( if $( if-let )* )+ for ...
+/// Split the items of a comprehension into two collections in a single pass.
+///
+/// This accepts the same syntax as [`comp!`], preceded by a boolean
+/// expression and a semicolon. Items for which the expression is `true` go
+/// into the first collection, and the rest go into the second one, just like
+/// with [`Iterator::partition`]. The expression may use any name introduced
+/// by the clauses, and is evaluated before the item expression.
+///
+/// The types of the collections may be given after the expression as
+/// `=> (A, B)`, and otherwise are inferred. Both must implement `Default`
+/// and `Extend`.
+///
+/// ```rust
+/// use py_comp::comp_partition;
+/// use std::collections::HashSet;
+///
+/// let rows = &[("a", Some(1)), ("b", None), ("c", Some(3))];
+///
+/// let (valid, invalid) = comp_partition!(
+///     value.is_some() => (Vec<_>, HashSet<_>);
+///     *name;
+///     for (name, value) in rows;
+/// );
+///
+/// assert_eq!(valid, vec!["a", "c"]);
+/// assert_eq!(invalid, ["b"].iter().copied().collect());
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_partition {
     (
+        $predicate: expr $(=> ($matching: ty, $other: ty $(,)?))?;
         $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr;
-        $(
-            if $condition: expr;
-            $( if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr; )*
-        )+
         for $($rest: tt)*
     ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .filter_map(move |$pattern|
-                comp!(@parse_if
-                    $item_expr;
-                    $(
-                        if $condition;
-                        $( if let $( $if_let_pattern )|+ = $if_let_expr; )*
-                    )+
-                    for $($rest)*
-                )
-            )
-            .flatten()
+        let mut targets $(: ($matching, $other))? = (
+            ::core::default::Default::default(),
+            ::core::default::Default::default(),
+        );
+        __py_comp_parse!(loops [{
+            if $predicate {
+                ::core::iter::Extend::extend(&mut targets.0, ::core::iter::once($item_expr));
+            } else {
+                ::core::iter::Extend::extend(&mut targets.1, ::core::iter::once($item_expr));
+            }
+        }] for $($rest)*);
+        targets
     }};
+}
 
-    // for in $( if-let $( if )* )+ for ...
-    (
-        $item_expr: expr;
-        for $pattern: pat in $into_iterator: expr;
-        $(
-            if let $( $if_let_pattern: pat )|+ = $if_let_expr: expr;
-            $( if $condition: expr; )*
-        )+
-        for $($rest: tt)*
-    ) => {{
-        let into_iterator = $into_iterator;
-        $crate::__py_comp_assert_impl_into_iter(&into_iterator);
-        into_iterator
-            .into_iter()
-            .filter_map(move |$pattern|
-                comp!(@parse_if
-                    $item_expr;
-                    $(
-                        if let $( $if_let_pattern )|+ = $if_let_expr;
-                        $( if $condition; )*
-                    )+
-                    for $($rest)*
-                )
-            )
-            .flatten()
+/// Split the tuple items of a comprehension into a tuple of collections in
+/// a single pass.
+///
+/// This accepts the same syntax as [`comp!`], with an item expression
+/// evaluating to a tuple. Each element of the tuple goes into the collection
+/// in the same position, just like with [`Iterator::unzip`], but for tuples
+/// of up to 12 elements.
+///
+/// The types of the collections may be given after the item expression as
+/// `=> (A, B, ...)`, and otherwise are inferred. All of them must implement
+/// `Default` and `Extend`.
+///
+/// ```rust
+/// use py_comp::comp_unzip;
+/// use std::collections::HashMap;
+///
+/// let people = &[("alice", 31, "paris"), ("bob", 25, "rome")];
+///
+/// let (names, ages, cities) = comp_unzip!(
+///     (*name, (*name, *age), *city) => (Vec<_>, HashMap<_, _>, Vec<_>);
+///     for (name, age, city) in people;
+/// );
+///
+/// assert_eq!(names, vec!["alice", "bob"]);
+/// assert_eq!(ages["bob"], 25);
+/// assert_eq!(cities, vec!["paris", "rome"]);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_unzip {
+    ($item_expr: expr $(=> ($($target: ty),+ $(,)?))?; for $($rest: tt)*) => {{
+        let mut targets $(: ($($target,)+))? = ::core::default::Default::default();
+        __py_comp_parse!(loops [{
+            $crate::extend::ExtendTuple::extend_tuple(&mut targets, $item_expr);
+        }] for $($rest)*);
+        targets
     }};
 }
+
+/// Find the first item of a comprehension, like Python's
+/// `next((... for ...), None)`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to `None` if the
+/// comprehension is empty. Instead of building an iterator, this expands to
+/// plain nested `for` loops, which are left as soon as the first item passes
+/// all of the clauses.
+///
+/// ```rust
+/// use py_comp::comp_find;
+///
+/// // Find a Pythagorean triple.
+/// let triple = comp_find!(
+///     (a, b, c);
+///     for c in 1..;
+///     for b in 1..c;
+///     for a in 1..b;
+///     if a * a + b * b == c * c;
+/// );
+///
+/// assert_eq!(triple, Some((3, 4, 5)));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_find {
+    ($item_expr: expr; for $($rest: tt)*) => {
+        'found: {
+            __py_comp_parse!(loops [{ break 'found Some($item_expr); }] for $($rest)*);
+            None
+        }
+    };
+}
+
+/// Sum the items of a comprehension, like Python's `sum(...)`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to a value of the
+/// same type as the items, so no type annotations are needed.
+///
+/// ```rust
+/// use py_comp::comp_sum;
+///
+/// let total = comp_sum!(x * y; for x in 1..=3; for y in 1..=3; if x != y);
+///
+/// assert_eq!(total, 22);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_sum {
+    ($($comp: tt)*) => {
+        $crate::__py_comp_sum(comp!($($comp)*))
+    };
+}
+
+/// Multiply the items of a comprehension, like Python's `math.prod(...)`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to a value of the
+/// same type as the items, so no type annotations are needed.
+///
+/// ```rust
+/// use py_comp::comp_product;
+///
+/// let factorial = comp_product!(n; for n in 1..=5_u64);
+///
+/// assert_eq!(factorial, 120);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_product {
+    ($($comp: tt)*) => {
+        $crate::__py_comp_product(comp!($($comp)*))
+    };
+}
+
+/// Find the minimum item of a comprehension, like Python's `min(...)`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to `None` if the
+/// comprehension is empty. Prefixing the item with `key =>` compares the
+/// items by that key instead, like `min(..., key=...)`.
+/// If several items are equally minimal, the first one is returned.
+///
+/// ```rust
+/// use py_comp::comp_min;
+///
+/// let words = &["apple", "fig", "banana"];
+///
+/// assert_eq!(comp_min!(w.len(); for w in words), Some(3));
+/// assert_eq!(comp_min!(w.len() => *w; for w in words), Some("fig"));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_min {
+    ($key: expr => $item_expr: expr; for $($rest: tt)*) => {
+        $crate::__py_comp_min_by_key(comp!(($key, $item_expr); for $($rest)*))
+    };
+
+    ($($comp: tt)*) => {
+        ::core::iter::Iterator::min(comp!($($comp)*))
+    };
+}
+
+/// Find the maximum item of a comprehension, like Python's `max(...)`.
+///
+/// This accepts the same syntax as [`comp!`], and evaluates to `None` if the
+/// comprehension is empty. Prefixing the item with `key =>` compares the
+/// items by that key instead, like `max(..., key=...)`.
+/// If several items are equally maximal, the first one is returned, unlike
+/// with [`Iterator::max`].
+///
+/// ```rust
+/// use py_comp::comp_max;
+///
+/// let words = &["apple", "fig", "banana"];
+///
+/// assert_eq!(comp_max!(w.len(); for w in words), Some(6));
+/// assert_eq!(comp_max!(w.len() => *w; for w in words), Some("banana"));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_max {
+    ($key: expr => $item_expr: expr; for $($rest: tt)*) => {
+        $crate::__py_comp_max_by_key(comp!(($key, $item_expr); for $($rest)*))
+    };
+
+    ($($comp: tt)*) => {
+        $crate::__py_comp_max(comp!($($comp)*))
+    };
+}
+
+/// Count the items of a comprehension, like Python's `sum(1 for ...)`.
+///
+/// This accepts the same syntax as [`comp!`], without the leading item
+/// expression.
+///
+/// ```rust
+/// use py_comp::comp_count;
+///
+/// let pairs = comp_count!(for x in 0..10; for y in x..10; if (x + y) % 3 == 0);
+///
+/// assert_eq!(pairs, 19);
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_count {
+    (for $($rest: tt)*) => {
+        ::core::iter::Iterator::count(comp!((); for $($rest)*))
+    };
+}
+
+/// Check whether any item of a comprehension is `true`, like Python's
+/// `any(...)`.
+///
+/// This accepts the same syntax as [`comp!`], with a boolean item.
+/// Evaluation stops at the first `true` item, without visiting the rest of
+/// any of the nesting levels.
+///
+/// ```rust
+/// use py_comp::comp_any;
+///
+/// let grid = &[[1, 2], [3, 4]];
+///
+/// assert!(comp_any!(*cell == 3; for row in grid; for cell in row));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_any {
+    ($($comp: tt)*) => {
+        ::core::iter::Iterator::any(&mut comp!($($comp)*), ::core::convert::identity)
+    };
+}
+
+/// Check whether all items of a comprehension are `true`, like Python's
+/// `all(...)`.
+///
+/// This accepts the same syntax as [`comp!`], with a boolean item.
+/// Evaluation stops at the first `false` item, without visiting the rest of
+/// any of the nesting levels.
+///
+/// ```rust
+/// use py_comp::comp_all;
+///
+/// let grid = &[[1, 2], [3, 4]];
+///
+/// assert!(comp_all!(*cell > 0; for row in grid; for cell in row));
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! comp_all {
+    ($($comp: tt)*) => {
+        ::core::iter::Iterator::all(&mut comp!($($comp)*), ::core::convert::identity)
+    };
+}
@@ -0,0 +1,44 @@
+//! The state of the iterator types which [`comp_struct!`] defines.
+//!
+//! Every `for` clause of the comprehension is a level of nested [`Level`]s,
+//! ending with a [`Last`] level for the final `for` clause. Each level holds
+//! the iterator over its source, and all but the final one also hold their
+//! current item, along with the values matched by their `if let` clauses,
+//! which are bound again for the nested levels.
+//!
+//! [`comp_struct!`]: ../macro.comp_struct.html
+
+/// The state of a `for` clause followed by another one, whose `if let`
+/// clauses match values of the types nested in `M`.
+#[derive(Clone, Debug)]
+pub struct Level<I: Iterator, M, Inner> {
+    #[doc(hidden)]
+    pub iter: Option<I>,
+    #[doc(hidden)]
+    pub item: Option<(I::Item, M)>,
+    #[doc(hidden)]
+    pub inner: Inner,
+}
+
+impl<I: Iterator, M, Inner: Default> Default for Level<I, M, Inner> {
+    fn default() -> Self {
+        Level {
+            iter: None,
+            item: None,
+            inner: Inner::default(),
+        }
+    }
+}
+
+/// The state of the final `for` clause.
+#[derive(Clone, Debug)]
+pub struct Last<I> {
+    #[doc(hidden)]
+    pub iter: Option<I>,
+}
+
+impl<I> Default for Last<I> {
+    fn default() -> Self {
+        Last { iter: None }
+    }
+}
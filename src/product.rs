@@ -0,0 +1,174 @@
+//! The iterator which nested `for` clauses without any other clauses expand
+//! to.
+//!
+//! A [`comp!`] level with no clauses other than its `for` clause, followed by
+//! another `for` clause, expands to a [`Product`] instead of a
+//! [`flat_map`][Iterator::flat_map]. Its `size_hint` never builds nested
+//! sources, so it only knows the items left in the current nested iterator,
+//! unless the nested levels are known not to depend on the items of the
+//! enclosing ones. That is the case when every nested level is a plain `for`
+//! clause with no other clauses, whose pattern binds every item and whose
+//! source is either marked `fixed`, such as `for b in fixed ys`, or written
+//! with literals only, such as `0..4` or `&[1, 2, 3]`. The length of the
+//! nested levels is then computed once, when the [`Product`] is created, and
+//! the `size_hint` is exact as long as the number of items fits in a `usize`.
+//!
+//! Like [`Flatten`][core::iter::Flatten], a [`Product`] is not an
+//! [`ExactSizeIterator`], since it may have more items than a `usize` can
+//! count even when all of its levels are exact.
+//!
+//! [`comp!`]: ../macro.comp.html
+
+use core::fmt;
+
+/// An iterator over the items of the iterators produced by a closure for
+/// every item of an outer iterator.
+///
+/// This yields the same items as [`Iterator::flat_map`], but computes its
+/// `size_hint` from the length of the nested iterators given by `L`, when it
+/// is known in advance. The upper bound is `None` only if it is unknown or
+/// does not fit in a `usize`.
+pub struct Product<I, F, U: IntoIterator, L = Dependent> {
+    outer: I,
+    f: F,
+    front: Option<U::IntoIter>,
+    len: L,
+}
+
+/// Create a [`Product`] of `outer` and the iterators produced by `f`, all of
+/// which have the length given by `len`.
+pub fn product<I, F, U, L>(outer: I, f: F, len: L) -> Product<I, F, U, L>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> U,
+    U: IntoIterator,
+    L: NestedLen,
+{
+    Product {
+        outer,
+        f,
+        front: None,
+        len,
+    }
+}
+
+/// The length of every nested iterator of a [`Product`], if it is known in
+/// advance.
+pub trait NestedLen {
+    /// The `size_hint` shared by all the nested iterators, or `None` if it may
+    /// differ between them.
+    fn size_hint(&self) -> Option<(usize, Option<usize>)>;
+}
+
+/// The nested iterators of a [`Product`] may depend on the items of the outer
+/// iterator, so their length is unknown until they are built.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dependent;
+
+/// The nested iterators of a [`Product`] do not depend on the items of the
+/// outer iterator, so they all share a `size_hint` computed in advance.
+#[derive(Debug, Clone, Copy)]
+pub struct Independent((usize, Option<usize>));
+
+impl Independent {
+    /// The length of nested levels whose sources have the size hints `hints`,
+    /// from the outermost to the innermost.
+    pub fn new(hints: &[(usize, Option<usize>)]) -> Self {
+        Independent(hints.iter().fold((1, Some(1)), |(lower, upper), hint| {
+            (
+                lower.saturating_mul(hint.0),
+                match (upper, hint.1) {
+                    (Some(upper), Some(hint_upper)) => upper.checked_mul(hint_upper),
+                    _ => None,
+                },
+            )
+        }))
+    }
+}
+
+impl NestedLen for Dependent {
+    fn size_hint(&self) -> Option<(usize, Option<usize>)> {
+        None
+    }
+}
+
+impl NestedLen for Independent {
+    fn size_hint(&self) -> Option<(usize, Option<usize>)> {
+        Some(self.0)
+    }
+}
+
+impl<I, F, U, L> Iterator for Product<I, F, U, L>
+where
+    I: Iterator,
+    F: FnMut(I::Item) -> U,
+    U: IntoIterator,
+    L: NestedLen,
+{
+    type Item = U::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(front) = &mut self.front {
+                if let Some(item) = front.next() {
+                    return Some(item);
+                }
+                self.front = None;
+            }
+            let item = self.outer.next()?;
+            self.front = Some((self.f)(item).into_iter());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (front_lower, front_upper) = self
+            .front
+            .as_ref()
+            .map_or((0, Some(0)), Iterator::size_hint);
+        let (outer_lower, outer_upper) = self.outer.size_hint();
+        if outer_upper == Some(0) {
+            return (front_lower, front_upper);
+        }
+        match self.len.size_hint() {
+            Some((lower, upper)) => (
+                front_lower.saturating_add(outer_lower.saturating_mul(lower)),
+                match (front_upper, outer_upper, upper) {
+                    (Some(front_upper), Some(outer_upper), Some(upper)) => outer_upper
+                        .checked_mul(upper)
+                        .and_then(|rest| front_upper.checked_add(rest)),
+                    _ => None,
+                },
+            ),
+            None => (front_lower, None),
+        }
+    }
+}
+
+impl<I: Clone, F: Clone, U, L: Clone> Clone for Product<I, F, U, L>
+where
+    U: IntoIterator,
+    U::IntoIter: Clone,
+{
+    fn clone(&self) -> Self {
+        Product {
+            outer: self.outer.clone(),
+            f: self.f.clone(),
+            front: self.front.clone(),
+            len: self.len.clone(),
+        }
+    }
+}
+
+impl<I: fmt::Debug, F, U, L: fmt::Debug> fmt::Debug for Product<I, F, U, L>
+where
+    U: IntoIterator,
+    U::IntoIter: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Product")
+            .field("outer", &self.outer)
+            .field("front", &self.front)
+            .field("len", &self.len)
+            .finish()
+    }
+}
@@ -0,0 +1,185 @@
+//! The report which [`comp_profiled!`] records into.
+//!
+//! [`comp_profiled!`]: ../macro.comp_profiled.html
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The statistics of a single clause, as recorded into a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    /// The source text of the clause.
+    pub label: &'static str,
+    /// The number of items which reached the clause. For a `for` clause,
+    /// this is the number of times its source was created.
+    pub received: usize,
+    /// The number of items which the clause let through. For a `for` clause,
+    /// this is the number of items of its sources which matched its pattern.
+    pub emitted: usize,
+    /// The time spent evaluating the clause. For a `for` clause, this
+    /// includes creating its sources and taking items from them.
+    pub time: Duration,
+}
+
+#[derive(Debug)]
+struct Stats {
+    label: &'static str,
+    received: Cell<usize>,
+    emitted: Cell<usize>,
+    time: Cell<Duration>,
+}
+
+/// The statistics of every `for`, `if` and `if let` clause of a
+/// [`comp_profiled!`] comprehension, in the order of the clauses.
+///
+/// The report is shared with the comprehension, and is updated as the
+/// comprehension is iterated. It is formatted as a table with one row per
+/// clause.
+///
+/// [`comp_profiled!`]: ../macro.comp_profiled.html
+#[derive(Debug, Clone)]
+pub struct Report {
+    clauses: Rc<[Stats]>,
+}
+
+impl Report {
+    /// Create an empty report for clauses with the given labels.
+    pub fn new(labels: &[&'static str]) -> Self {
+        Report {
+            clauses: labels
+                .iter()
+                .map(|&label| Stats {
+                    label,
+                    received: Cell::new(0),
+                    emitted: Cell::new(0),
+                    time: Cell::new(Duration::ZERO),
+                })
+                .collect(),
+        }
+    }
+
+    /// The statistics recorded so far for every clause.
+    pub fn clauses(&self) -> Vec<Clause> {
+        self.clauses
+            .iter()
+            .map(|stats| Clause {
+                label: stats.label,
+                received: stats.received.get(),
+                emitted: stats.emitted.get(),
+                time: stats.time.get(),
+            })
+            .collect()
+    }
+
+    fn time<T>(&self, clause: usize, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let stats = &self.clauses[clause];
+        stats.time.set(stats.time.get() + start.elapsed());
+        result
+    }
+
+    fn count(cell: &Cell<usize>) {
+        cell.set(cell.get() + 1);
+    }
+
+    /// Create the source of a `for` clause, recording the time spent taking
+    /// items from it.
+    #[doc(hidden)]
+    pub fn __py_comp_source<I: IntoIterator>(
+        &self,
+        clause: usize,
+        f: impl FnOnce() -> I,
+    ) -> Profiled<I::IntoIter> {
+        Self::count(&self.clauses[clause].received);
+        Profiled {
+            iter: self.time(clause, || f().into_iter()),
+            report: self.clone(),
+            clause,
+        }
+    }
+
+    /// Evaluate the condition of an `if` clause.
+    #[doc(hidden)]
+    pub fn __py_comp_condition(&self, clause: usize, f: impl FnOnce() -> bool) -> bool {
+        Self::count(&self.clauses[clause].received);
+        let condition = self.time(clause, f);
+        if condition {
+            Self::count(&self.clauses[clause].emitted);
+        }
+        condition
+    }
+
+    /// Evaluate the expression of an `if let` clause.
+    #[doc(hidden)]
+    pub fn __py_comp_value<T>(&self, clause: usize, f: impl FnOnce() -> T) -> T {
+        Self::count(&self.clauses[clause].received);
+        self.time(clause, f)
+    }
+
+    /// Record that an item matched the pattern of a `for` or `if let` clause.
+    #[doc(hidden)]
+    pub fn __py_comp_matched(&self, clause: usize) -> bool {
+        Self::count(&self.clauses[clause].emitted);
+        true
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clauses = self.clauses();
+        let width = clauses
+            .iter()
+            .map(|clause| clause.label.chars().count())
+            .chain(Some("clause".len()))
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "{:<width$}  {:>10}  {:>10}  {:>12}",
+            "clause",
+            "received",
+            "emitted",
+            "time",
+            width = width
+        )?;
+        for clause in &clauses {
+            writeln!(
+                f,
+                "{:<width$}  {:>10}  {:>10}  {:>12}",
+                clause.label,
+                clause.received,
+                clause.emitted,
+                format!("{:?}", clause.time),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The source of a `for` clause of a [`comp_profiled!`] comprehension,
+/// recording the time spent taking items from it into a [`Report`].
+///
+/// [`comp_profiled!`]: ../macro.comp_profiled.html
+#[derive(Debug, Clone)]
+pub struct Profiled<I> {
+    iter: I,
+    report: Report,
+    clause: usize,
+}
+
+impl<I: Iterator> Iterator for Profiled<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let iter = &mut self.iter;
+        self.report.time(self.clause, || iter.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
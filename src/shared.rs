@@ -0,0 +1,245 @@
+//! The [`Shared`] sources which [`comp_owned!`] moves into a comprehension.
+//!
+//! [`comp_owned!`]: ../macro.comp_owned.html
+
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+use std::fmt;
+use std::hash::BuildHasher;
+use std::iter::FusedIterator;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A collection shared between the levels of a comprehension.
+///
+/// This is a reference-counted pointer to the collection, which is cheap to
+/// clone, and dereferences to the collection. Iterating over it by value
+/// yields clones of its items, so the iterator owns a share of the collection
+/// instead of borrowing it. Sequences are iterated lazily by index, while the
+/// items of maps, sets and strings are cloned into a buffer first.
+///
+/// A `Shared` collection is `Send` and `Sync` when the collection is both.
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    /// Move a collection into a new shared pointer.
+    pub fn new(value: T) -> Self {
+        Shared(Arc::new(value))
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Shared").field(&self.0).finish()
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Shared::new(value)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Shared<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (**self).into_iter()
+    }
+}
+
+/// A sequence whose items can be cloned by index.
+pub trait Sequence {
+    /// The type of the items.
+    type Item;
+
+    /// The number of items in the sequence.
+    fn len(&self) -> usize;
+
+    /// Whether the sequence has no items.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clone the item at an index, which is less than the length.
+    fn get(&self, index: usize) -> Self::Item;
+}
+
+impl<T: Clone> Sequence for Vec<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> T {
+        self[index].clone()
+    }
+}
+
+impl<T: Clone> Sequence for Box<[T]> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> T {
+        self[index].clone()
+    }
+}
+
+impl<T: Clone, const N: usize> Sequence for [T; N] {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn get(&self, index: usize) -> T {
+        self[index].clone()
+    }
+}
+
+impl<T: Clone> Sequence for VecDeque<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> T {
+        self[index].clone()
+    }
+}
+
+/// An iterator over clones of the items of a [`Shared`] sequence.
+pub struct IntoIter<S> {
+    shared: Shared<S>,
+    front: usize,
+    back: usize,
+}
+
+impl<S: Sequence> Iterator for IntoIter<S> {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.shared.get(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<S::Item> {
+        self.front += n.min(self.back - self.front);
+        self.next()
+    }
+}
+
+impl<S: Sequence> DoubleEndedIterator for IntoIter<S> {
+    fn next_back(&mut self) -> Option<S::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.shared.get(self.back))
+    }
+}
+
+impl<S: Sequence> ExactSizeIterator for IntoIter<S> {}
+
+impl<S: Sequence> FusedIterator for IntoIter<S> {}
+
+impl<S> Clone for IntoIter<S> {
+    fn clone(&self) -> Self {
+        IntoIter {
+            shared: self.shared.clone(),
+            front: self.front,
+            back: self.back,
+        }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for IntoIter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoIter")
+            .field("shared", &self.shared)
+            .field("front", &self.front)
+            .field("back", &self.back)
+            .finish()
+    }
+}
+
+macro_rules! impl_into_iter_sequence {
+    ($([$($generics: tt)*] $sequence: ty;)+) => {$(
+        impl<$($generics)*> IntoIterator for Shared<$sequence> {
+            type Item = T;
+            type IntoIter = IntoIter<$sequence>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                let back = Sequence::len(&*self);
+                IntoIter {
+                    shared: self,
+                    front: 0,
+                    back,
+                }
+            }
+        }
+    )+};
+}
+
+impl_into_iter_sequence! {
+    [T: Clone] Vec<T>;
+    [T: Clone] Box<[T]>;
+    [T: Clone, const N: usize] [T; N];
+    [T: Clone] VecDeque<T>;
+}
+
+macro_rules! impl_into_iter_buffered {
+    ($([$($generics: tt)*] $collection: ty => $item: ty, |$items: ident| $clone: expr;)+) => {$(
+        impl<$($generics)*> IntoIterator for Shared<$collection> {
+            type Item = $item;
+            type IntoIter = std::vec::IntoIter<$item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                let $items = &*self;
+                $clone.collect::<Vec<_>>().into_iter()
+            }
+        }
+    )+};
+}
+
+impl_into_iter_buffered! {
+    [K: Clone, V: Clone, S: BuildHasher] HashMap<K, V, S> => (K, V),
+        |map| map.iter().map(|(k, v)| (k.clone(), v.clone()));
+    [K: Clone, V: Clone] BTreeMap<K, V> => (K, V),
+        |map| map.iter().map(|(k, v)| (k.clone(), v.clone()));
+    [T: Clone, S: BuildHasher] HashSet<T, S> => T, |set| set.iter().cloned();
+    [T: Clone] BTreeSet<T> => T, |set| set.iter().cloned();
+    [T: Clone] BinaryHeap<T> => T, |heap| heap.iter().cloned();
+    [T: Clone] LinkedList<T> => T, |list| list.iter().cloned();
+    [] String => char, |string| string.chars();
+}
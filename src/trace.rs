@@ -0,0 +1,103 @@
+//! Helpers for the spans and events which [`comp!`] emits when the `tracing`
+//! feature is enabled.
+//!
+//! With this feature, every `for` clause of a [`comp!`] comprehension runs
+//! in a `comp` span at the `DEBUG` level, and every item it takes runs in a
+//! nested `comp_item` span at the `TRACE` level. Both spans carry the source
+//! text of the clause in their `clause` field. The item span also carries the
+//! `Debug` representation of the item taken from the source, before it is
+//! matched against the pattern of the clause, in its `item` field, and that of
+//! every name bound by the pattern in a field of the same name, or `_` when
+//! the value is not `Debug`. When the pattern may be refutable, the names are
+//! recorded through [`Span::current`] once the item matches, so they are only
+//! found by subscribers which track the current span.
+//!
+//! Every item rejected by the pattern of a `for` clause, or by an `if` or
+//! `if let` clause, emits a `DEBUG` event inside the span of that item, with
+//! the source text of the rejecting clause in its `clause` field, and a field
+//! for every name bound by the pattern of the `for` clause and by the `if let`
+//! clauses before the rejecting one.
+//!
+//! The names are collected from the tokens of the first alternative of each
+//! pattern, skipping paths, field names, literals, `ref` and `mut`. A name
+//! which is not followed by parentheses or braces can not be told apart from
+//! a constant or a unit variant, so it is recorded as well, except for `None`.
+//!
+//! [`Span::current`]: https://docs.rs/tracing/0.1/tracing/struct.Span.html#method.current
+//! [`comp!`]: ../macro.comp.html
+
+use core::fmt;
+use tracing::Span;
+
+/// An iterator which enters a span while producing every item.
+///
+/// The iterators of the `for` clauses of [`comp!`] are wrapped in this when
+/// the `tracing` feature is enabled.
+///
+/// [`comp!`]: ../macro.comp.html
+#[derive(Debug, Clone)]
+pub struct Traced<I> {
+    span: Span,
+    iter: I,
+}
+
+impl<I> Traced<I> {
+    /// Wrap `iter`, entering `span` while producing every item.
+    pub fn new(span: Span, iter: I) -> Self {
+        Traced { span, iter }
+    }
+}
+
+impl<I: Iterator> Iterator for Traced<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let _entered = self.span.enter();
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for Traced<I> {}
+
+// Autoref-based specialization: `(&__PyCompItem(&item)).__py_comp_debug()`
+// resolves to the `Debug` representation of the item when it has one, and to
+// a placeholder otherwise.
+
+#[doc(hidden)]
+pub struct __PyCompItem<'a, T>(pub &'a T);
+
+struct Opaque;
+
+impl fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("_")
+    }
+}
+
+#[doc(hidden)]
+pub trait __PyCompDebugKind {
+    fn __py_comp_debug(&self) -> &dyn fmt::Debug;
+}
+
+impl<T: fmt::Debug> __PyCompDebugKind for __PyCompItem<'_, T> {
+    #[inline(always)]
+    fn __py_comp_debug(&self) -> &dyn fmt::Debug {
+        self.0
+    }
+}
+
+#[doc(hidden)]
+pub trait __PyCompOpaqueKind {
+    fn __py_comp_debug(&self) -> &dyn fmt::Debug;
+}
+
+impl<T> __PyCompOpaqueKind for &__PyCompItem<'_, T> {
+    #[inline(always)]
+    fn __py_comp_debug(&self) -> &dyn fmt::Debug {
+        &Opaque
+    }
+}
@@ -3,9 +3,39 @@
 //!
 //! For more complex tests, drill down to the sub modules.
 
+// These tests are older than the lints below, and are kept as they were
+// written.
+#![allow(ellipsis_inclusive_range_patterns, renamed_and_removed_lints)]
+#![allow(
+    clippy::double_comparisons,
+    clippy::into_iter_on_ref,
+    clippy::map_identity
+)]
+
 use py_comp::comp;
 
+mod test_borrowed;
+mod test_boxed;
+mod test_clauses;
+mod test_clone;
+mod test_compile_fail;
+mod test_cursor;
+mod test_def;
+mod test_explain;
+mod test_extend;
+mod test_for_modifiers;
 mod test_if_chains;
+mod test_indexed;
+mod test_lineage;
+mod test_loops;
+mod test_owned;
+mod test_profile;
+mod test_reductions;
+mod test_refutable;
+mod test_size_hint;
+mod test_struct;
+#[cfg(feature = "tracing")]
+mod test_tracing;
 
 /// This is a stand-in for any type that does not implement Copy or Clone.
 /// Using this type we can know that our implementation does not depend on
@@ -0,0 +1,81 @@
+//! Test the `ref` prefix of `comp!`, which borrows the listed locals.
+
+use py_comp::{comp, comp_sum};
+use std::collections::HashMap;
+
+#[test]
+fn for_for_ref_vec() {
+    let words = vec![String::from("a"), String::from("bc")];
+    let suffixes = vec![String::from("!"), String::from("?")];
+
+    let items: Vec<String> = comp!(
+        ref suffixes;
+        format!("{}{}", word, suffix);
+        for word in &words;
+        for suffix in suffixes;
+    )
+    .collect();
+
+    assert_eq!(items, vec!["a!", "a?", "bc!", "bc?"]);
+    // The locals are only borrowed.
+    assert_eq!(suffixes.len(), 2);
+}
+
+#[test]
+fn for_if_for_ref_map() {
+    let groups: HashMap<u32, Vec<String>> = vec![
+        (1, vec![String::from("one")]),
+        (2, vec![String::from("two"), String::from("deux")]),
+    ]
+    .into_iter()
+    .collect();
+    let keys = vec![2, 3, 1];
+
+    let items: Vec<&String> = comp!(
+        ref groups;
+        name;
+        for key in &keys;
+        if let Some(names) = groups.get(key);
+        for name in names;
+    )
+    .collect();
+
+    assert_eq!(items, vec!["two", "deux", "one"]);
+}
+
+#[test]
+fn for_ref_yield() {
+    let prefix = String::from("item ");
+
+    let items: Vec<String> = comp!(ref prefix; format!("{}{}", prefix, x); for x in 0..2).collect();
+
+    assert_eq!(items, vec!["item 0", "item 1"]);
+}
+
+#[test]
+fn for_for_ref_several() {
+    let xs = vec![1, 2];
+    let ys = vec![10, 20];
+    let zs = vec![100];
+
+    let items: Vec<i32> = comp!(
+        ref ys, zs;
+        x + y + z;
+        for x in &xs;
+        for y in ys;
+        for z in zs;
+    )
+    .collect();
+
+    assert_eq!(items, vec![111, 121, 112, 122]);
+}
+
+#[test]
+fn for_for_ref_reduction() {
+    let rows = vec![vec![1, 2], vec![3]];
+    let weights = vec![1, 10];
+
+    let total = comp_sum!(ref weights; x * w; for row in &rows; for x in row; for w in weights);
+
+    assert_eq!(total, 66);
+}
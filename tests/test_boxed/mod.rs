@@ -0,0 +1,74 @@
+//! Test the boxed comprehensions built by `comp_boxed!` and `comp_dyn!`.
+
+use py_comp::boxed::{Boxed, BoxedSend};
+use py_comp::{comp, comp_boxed, comp_dyn};
+
+fn words<'a>(text: &'a [&'a str], long: bool) -> Boxed<'a, char> {
+    if long {
+        comp_boxed!(c; for word in text; if word.len() > 2; for c in word.chars())
+    } else {
+        comp_boxed!(word.chars().next().unwrap(); for word in text; if !word.is_empty())
+    }
+}
+
+#[test]
+fn branches_unify() {
+    let text = ["ab", "cde", "f"];
+
+    assert_eq!(words(&text, true).collect::<String>(), "cde");
+    assert_eq!(words(&text, false).collect::<String>(), "acf");
+}
+
+#[test]
+fn for_if_for_if_for_per_level() {
+    let x = &[1, 2, 3];
+    let y = &[10, 20];
+
+    let expected: Vec<(i32, i32, i32)> = comp!(
+        (*a, *b, c);
+        for a in x;
+        if a % 2 == 1;
+        for b in y;
+        for c in 0..*a;
+        if c != 1;
+    )
+    .collect();
+
+    let boxed: Boxed<'_, (i32, i32, i32)> = comp_boxed!(
+        per level (*a, *b, c);
+        for a in x;
+        if a % 2 == 1;
+        for b in y;
+        for c in 0..*a;
+        if c != 1;
+    );
+
+    assert_eq!(boxed.collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn for_take_scan_for_per_level() {
+    let rows = &[vec![1, 2], vec![3], vec![4, 5, 6]];
+
+    let items: Vec<(i32, i32)> =
+        comp_boxed!(per level (total, *x); for row in rows; scan total = 0 => total + 1; take 2; for x in row)
+            .collect();
+
+    assert_eq!(items, vec![(1, 1), (1, 2), (2, 3)]);
+}
+
+#[test]
+fn dyn_send_across_threads() {
+    let data = vec![vec![1u64, 2], vec![3]];
+
+    let sum = std::thread::scope(|scope| {
+        let items: BoxedSend<'_, u64> =
+            comp_dyn!(per level *x * 10; for row in &data; for x in row);
+        scope.spawn(move || items.sum::<u64>()).join().unwrap()
+    });
+
+    assert_eq!(sum, 60);
+
+    let flat: BoxedSend<'static, u32> = comp_dyn!(x; for x in 0..3u32);
+    assert_eq!(std::thread::spawn(move || flat.count()).join().unwrap(), 3);
+}
@@ -0,0 +1,7 @@
+//! This module contains tests for the clauses which may follow a `for` clause
+//! other than `if` and `if let`. These clauses keep state for every
+//! instantiation of the level they appear in, so the tests here focus on how
+//! that state interacts with filters and with nested `for` clauses.
+
+mod test_scan;
+mod test_slicing;
@@ -0,0 +1,175 @@
+//! Test the `scan` clause.
+
+use py_comp::comp;
+
+use crate::Foo;
+
+#[test]
+fn for_scan() {
+    let items: Vec<i32> = comp!(total; for x in 1..=5; scan total = 0 => total + x).collect();
+
+    assert_eq!(items, vec![1, 3, 6, 10, 15]);
+}
+
+#[test]
+fn for_scan_prefix_maximum() {
+    let samples = &[3, 1, 4, 1, 5, 9, 2, 6];
+
+    let items: Vec<i32> = comp!(
+        highest;
+        for x in samples;
+        scan highest = i32::MIN => highest.max(*x);
+    )
+    .collect();
+
+    assert_eq!(items, vec![3, 3, 4, 4, 5, 9, 9, 9]);
+}
+
+#[test]
+fn for_scan_if() {
+    let samples = &[3, 1, 4, 1, 5, 9, 2, 6];
+
+    // Positions where a new maximum was reached.
+    let items: Vec<usize> = comp!(
+        i;
+        for (i, x) in samples.iter().enumerate();
+        scan highest = None => highest.max(Some(*x));
+        if highest == Some(*x);
+    )
+    .collect();
+
+    assert_eq!(items, vec![0, 2, 4, 5]);
+}
+
+#[test]
+fn for_if_scan() {
+    // Only the items passing the filter are accumulated.
+    let items: Vec<(i32, i32)> = comp!(
+        (x, total);
+        for x in 1..=6;
+        if x % 2 == 0;
+        scan total = 0 => total + x;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(2, 2), (4, 6), (6, 12)]);
+}
+
+#[test]
+fn for_scan_take() {
+    let items: Vec<u64> = comp!(
+        factorial;
+        for n in 1..;
+        scan factorial = 1 => factorial * n;
+        take 5;
+    )
+    .collect();
+
+    assert_eq!(items, vec![1, 2, 6, 24, 120]);
+}
+
+#[test]
+fn for_scan_state_machine() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum State {
+        Text,
+        Quoted,
+    }
+
+    let text = "a \"b c\" d";
+
+    let quoted: String = comp!(
+        c;
+        for c in text.chars();
+        scan state = State::Text => match (state, c) {
+            (State::Text, '"') => State::Quoted,
+            (State::Quoted, '"') => State::Text,
+            (state, _) => state,
+        };
+        if state == State::Quoted && c != '"';
+    )
+    .collect();
+
+    assert_eq!(quoted, "b c");
+}
+
+#[test]
+fn for_scan_not_copy() {
+    let words = &["a", "b", "c"];
+
+    let items: Vec<String> = comp!(
+        prefix;
+        for word in words;
+        scan prefix = String::new() => prefix + word;
+    )
+    .collect();
+
+    assert_eq!(items, vec!["a", "ab", "abc"]);
+}
+
+#[test]
+fn for_scan_for() {
+    let items: Vec<(i32, i32)> = comp!(
+        (total, y);
+        for x in 1..=3;
+        scan total = 0 => total + x;
+        for y in 0..x;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(1, 0), (3, 0), (3, 1), (6, 0), (6, 1), (6, 2)]);
+}
+
+#[test]
+fn for_for_scan() {
+    let rows = &[[1, 2, 3], [4, 5, 6]];
+
+    // The accumulator of the inner level is reset for every row.
+    let items: Vec<i32> = comp!(
+        total;
+        for row in rows;
+        for x in row;
+        scan total = 0 => total + x;
+    )
+    .collect();
+
+    assert_eq!(items, vec![1, 3, 6, 4, 9, 15]);
+}
+
+#[test]
+fn init_from_outer_binding() {
+    let items: Vec<i32> = comp!(
+        total;
+        for start in &[10, 20];
+        for x in 1..=2;
+        scan total = *start => total + x;
+    )
+    .collect();
+
+    assert_eq!(items, vec![11, 13, 21, 23]);
+}
+
+#[test]
+fn for_scan_scan() {
+    let items: Vec<(i32, i32)> = comp!(
+        (count, total);
+        for x in 1..=4;
+        scan count = 0 => count + 1;
+        scan total = 0 => total + x * count;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(1, 1), (2, 5), (3, 14), (4, 30)]);
+}
+
+/// Like `various_forms_of_usage` in the root module, this covers the parse
+/// paths of the clause rather than its results.
+#[test]
+fn various_forms_of_usage() {
+    let x = &[Foo(1), Foo(2)];
+    let y = &[[Foo(1), Foo(2)], [Foo(3), Foo(4)]];
+
+    let _ = comp!(a; for a in x; scan n = 0 => n + a.0);
+    let _ = comp!(n; for a in x; scan n = 0 => n + a.0; if n > 1;);
+    let _ = comp!((n, a); for x in y; scan n = 0 => n + 1; for a in x);
+}
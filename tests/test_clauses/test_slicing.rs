@@ -0,0 +1,191 @@
+//! Test the `take`, `skip` and `step` clauses.
+
+use py_comp::comp;
+
+use crate::Foo;
+
+#[test]
+fn for_take() {
+    let items: Vec<i32> = comp!(x; for x in 0..10; take 3).collect();
+
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[test]
+fn for_take_infinite() {
+    let items: Vec<i32> = comp!(x; for x in 0..; take 3).collect();
+
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[test]
+fn for_skip() {
+    let items: Vec<i32> = comp!(x; for x in 0..5; skip 3).collect();
+
+    assert_eq!(items, vec![3, 4]);
+}
+
+#[test]
+fn for_step() {
+    let items: Vec<i32> = comp!(x; for x in 0..10; step 3).collect();
+
+    assert_eq!(items, vec![0, 3, 6, 9]);
+}
+
+#[test]
+#[should_panic(expected = "`step` clause must not be zero")]
+fn for_step_zero() {
+    let _ = comp!(x; for x in 0..10; step 0);
+}
+
+#[test]
+fn for_if_take() {
+    let items: Vec<i32> = comp!(x; for x in 0..; if x % 2 == 1; take 3).collect();
+
+    assert_eq!(items, vec![1, 3, 5]);
+}
+
+#[test]
+fn for_take_if() {
+    let items: Vec<i32> = comp!(x; for x in 0..; take 6; if x % 2 == 1).collect();
+
+    assert_eq!(items, vec![1, 3, 5]);
+}
+
+#[test]
+fn for_if_let_skip_step() {
+    let iterable = &[Some(1), None, Some(2), Some(3), None, Some(4), Some(5)];
+
+    let items: Vec<i32> = comp!(
+        *x;
+        for item in iterable;
+        if let Some(x) = item;
+        skip 1;
+        step 2
+    )
+    .collect();
+
+    assert_eq!(items, vec![2, 4]);
+}
+
+#[test]
+fn for_skip_take_step() {
+    let items: Vec<i32> = comp!(x; for x in 0..; skip 2; take 5; step 2).collect();
+
+    assert_eq!(items, vec![2, 4, 6]);
+}
+
+#[test]
+fn for_take_for() {
+    let parents = &[[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+
+    let items: Vec<(usize, i32)> = comp!(
+        (i, *child);
+        for (i, children) in parents.iter().enumerate();
+        take 2;
+        for child in children;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(0, 1), (0, 2), (0, 3), (1, 4), (1, 5), (1, 6)]);
+}
+
+#[test]
+fn for_for_take() {
+    let parents = &[[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+
+    // The counter of the inner `take` is reset for every parent.
+    let items: Vec<i32> = comp!(
+        *child;
+        for children in parents;
+        for child in children;
+        take 2;
+    )
+    .collect();
+
+    assert_eq!(items, vec![1, 2, 4, 5, 7, 8]);
+}
+
+#[test]
+fn for_if_step_for_if_skip() {
+    let items: Vec<(i32, i32)> = comp!(
+        (x, y);
+        for x in 0..10;
+        if x > 0;
+        step 4;
+        for y in 0..x;
+        if y % 2 == 0;
+        skip 1;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(5, 2), (5, 4), (9, 2), (9, 4), (9, 6), (9, 8)]);
+}
+
+#[test]
+fn limit_from_outer_binding() {
+    let items: Vec<(i32, i32)> = comp!(
+        (x, y);
+        for x in 0..4;
+        for y in 0..;
+        take x as usize;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(1, 0), (2, 0), (2, 1), (3, 0), (3, 1), (3, 2)]);
+}
+
+#[test]
+fn take_does_not_pull_another_item() {
+    let mut pulled = 0;
+
+    let items: Vec<i32> = comp!(
+        x;
+        for x in (0..10).inspect(|_| pulled += 1);
+        if x % 2 == 0;
+        take 3;
+    )
+    .collect();
+
+    assert_eq!(items, vec![0, 2, 4]);
+    assert_eq!(pulled, 5);
+}
+
+#[test]
+fn take_zero_does_not_pull_any_item() {
+    let mut pulled = 0;
+
+    let items: Vec<i32> = comp!(x; for x in (0..10).inspect(|_| pulled += 1); take 0).collect();
+
+    assert!(items.is_empty());
+    assert_eq!(pulled, 0);
+}
+
+#[test]
+fn take_for_does_not_pull_another_parent() {
+    let mut pulled = 0;
+
+    let items: Vec<(i32, i32)> = comp!(
+        (x, y);
+        for x in (0..10).inspect(|_| pulled += 1);
+        take 2;
+        for y in 0..2;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+    assert_eq!(pulled, 2);
+}
+
+/// Like `various_forms_of_usage` in the root module, this covers the parse
+/// paths of the clauses rather than their results.
+#[test]
+fn various_forms_of_usage() {
+    let x = &[Foo(1), Foo(2)];
+    let y = &[[Foo(1), Foo(2)], [Foo(3), Foo(4)]];
+
+    let _ = comp!(a; for a in x; take 1);
+    let _ = comp!(a; for a in x; skip 1; step 2;);
+    let _ = comp!(a; for x in y; if x[0] == Foo(123); take 1; for a in x);
+    let _ = comp!(a; for x in y; for a in x; if let Foo(1) = a; skip 1; take 1;);
+}
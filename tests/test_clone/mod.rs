@@ -0,0 +1,105 @@
+//! Test that comprehension iterators are `Clone` when their sources and
+//! captured values are, and that a clone resumes from the same position
+//! independently of the original.
+
+use py_comp::{comp, comp_owned};
+
+/// Advance a clone of the iterator to its end, and check that the original
+/// still yields the same items afterwards.
+fn assert_forks<I>(iter: &mut I, skip: usize)
+where
+    I: Iterator + Clone,
+    I::Item: PartialEq + std::fmt::Debug,
+{
+    for _ in 0..skip {
+        iter.next();
+    }
+    let fork: Vec<I::Item> = iter.clone().collect();
+    let rest: Vec<I::Item> = iter.collect();
+    assert_eq!(fork, rest);
+}
+
+#[test]
+fn for_clone() {
+    let x = &[1, 2, 3, 4];
+
+    let mut items = comp!(a * 10; for a in x);
+    assert_eq!(items.next(), Some(10));
+
+    let mut fork = items.clone();
+    assert_eq!(fork.next(), Some(20));
+    assert_eq!(fork.next(), Some(30));
+    assert_eq!(items.next(), Some(20));
+    assert_eq!(fork.collect::<Vec<i32>>(), vec![40]);
+    assert_eq!(items.collect::<Vec<i32>>(), vec![30, 40]);
+}
+
+#[test]
+fn for_for_clone_mid_level() {
+    let x = &[1, 2, 3];
+    let y = &[10, 20, 30];
+
+    let mut items = comp!((a, b); for a in x; for b in y);
+    assert_eq!(items.next(), Some((&1, &10)));
+    assert_eq!(items.next(), Some((&1, &20)));
+
+    let mut fork = items.clone();
+    assert_eq!(fork.next(), Some((&1, &30)));
+    assert_eq!(fork.next(), Some((&2, &10)));
+    assert_eq!(items.next(), Some((&1, &30)));
+    assert_eq!(fork.count(), 5);
+    assert_eq!(items.count(), 6);
+}
+
+#[test]
+fn for_if_for_if_clone() {
+    let x = &[1, 2, 3, 4];
+
+    let mut items = comp!((a, b); for a in x; if a % 2 == 0; for b in 0..*a; if b % 2 == 1);
+
+    assert_forks(&mut items, 0);
+
+    let mut items = comp!((a, b); for a in x; if a % 2 == 0; for b in 0..*a; if b % 2 == 1);
+
+    assert_forks(&mut items, 2);
+}
+
+#[test]
+fn for_take_skip_scan_clone_counters() {
+    let mut items = comp!(total; for x in 1..; skip 1; scan total = 0 => total + x; take 4);
+    assert_eq!(items.next(), Some(2));
+
+    let fork = items.clone();
+    assert_eq!(items.next(), Some(5));
+    assert_eq!(fork.collect::<Vec<i32>>(), vec![5, 9, 14]);
+    assert_eq!(items.collect::<Vec<i32>>(), vec![9, 14]);
+}
+
+#[test]
+fn for_window_for_chunks_clone() {
+    let x = &[1, 2, 3, 4];
+
+    let mut items = comp!(
+        (a + b, chunk);
+        for [a, b] in window 2 of x;
+        for chunk in chunks 2 of 0..a + b;
+    );
+
+    assert_forks(&mut items, 3);
+}
+
+#[test]
+fn owned_clone() {
+    let names = vec![String::from("a"), String::from("b"), String::from("c")];
+
+    let mut pairs =
+        comp_owned!(move names; a.clone() + &b; for a in names; for b in names; if a != b);
+    assert_eq!(pairs.next(), Some(String::from("ab")));
+
+    let fork = pairs.clone();
+    drop(pairs);
+    assert_eq!(
+        fork.collect::<Vec<String>>(),
+        vec!["ac", "ba", "bc", "ca", "cb"]
+    );
+}
@@ -0,0 +1,7 @@
+//! Test the compile errors reported for malformed comprehensions.
+
+#[test]
+fn compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/test_compile_fail/ui/*.rs");
+}
@@ -0,0 +1,6 @@
+use py_comp::comp;
+
+fn main() {
+    let xs = vec![Some(1), None];
+    let _ = comp!(x; for x in &xs, if x.is_some());
+}
@@ -0,0 +1,6 @@
+use py_comp::comp;
+
+fn main() {
+    let rows = vec![vec![1, 2], vec![3]];
+    let _ = comp!(*x; for row in &rows; for x in &row);
+}
@@ -0,0 +1,9 @@
+use py_comp::comp;
+
+fn main() {
+    let items = {
+        let names = vec![String::from("a")];
+        comp!(ref names; (x, name); for x in 0..2; for name in names)
+    };
+    let _ = items.count();
+}
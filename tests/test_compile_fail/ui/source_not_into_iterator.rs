@@ -0,0 +1,6 @@
+use py_comp::comp;
+
+fn main() {
+    let word = String::from("word");
+    let _ = comp!(c; for c in word);
+}
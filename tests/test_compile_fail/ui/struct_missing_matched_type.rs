@@ -0,0 +1,12 @@
+use py_comp::comp_struct;
+
+comp_struct! {
+    struct Parsed<'a>(words: &'a [&'a str]) -> u32 {
+        n;
+        for word in words => &'a [&'a str];
+        if let Ok(count) = word.parse::<u32>();
+        for n in 0..count => core::ops::Range<u32>;
+    }
+}
+
+fn main() {}
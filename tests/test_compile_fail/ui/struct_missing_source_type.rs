@@ -0,0 +1,10 @@
+use py_comp::comp_struct;
+
+comp_struct! {
+    struct Squares(n: u32) -> u32 {
+        x * x;
+        for x in 0..n;
+    }
+}
+
+fn main() {}
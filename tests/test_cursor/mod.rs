@@ -0,0 +1,127 @@
+//! Test the `comp_resumable!` macro, by checking that stopping a
+//! comprehension after every item and resuming it from its cursor yields the
+//! same items as an uninterrupted run.
+
+use py_comp::{comp, comp_resumable, cursor::Cursor};
+
+/// Check that resuming `build()` after each of its items yields the
+/// remaining items, going through the textual form of the cursor.
+fn assert_resumes<I, L>(build: impl Fn() -> py_comp::cursor::Resumable<L>, expected: Vec<I>)
+where
+    I: PartialEq + std::fmt::Debug,
+    L: py_comp::cursor::Level<Item = I>,
+{
+    assert_eq!(build().collect::<Vec<I>>(), expected);
+
+    for stop in 0..=expected.len() {
+        let mut first = build();
+        let mut items: Vec<I> = first.by_ref().take(stop).collect();
+
+        let checkpoint = first.cursor().to_string();
+        let cursor: Cursor = checkpoint.parse().unwrap();
+        items.extend(build().resume(&cursor));
+
+        assert_eq!(items, expected, "resumed at {}", checkpoint);
+    }
+}
+
+#[test]
+fn for_resume() {
+    assert_resumes(
+        || comp_resumable!(x * 2; for x in 0..5),
+        vec![0, 2, 4, 6, 8],
+    );
+}
+
+#[test]
+fn for_for_for_resume() {
+    let x = &[1, 2];
+    let y = &[3, 4, 5];
+
+    let expected: Vec<(i32, i32, u8)> =
+        comp!((*a, *b, c); for a in x; for b in y; for c in 0..2u8).collect();
+
+    assert_resumes(
+        || comp_resumable!((*a, *b, c); for a in x; for b in y; for c in 0..2u8),
+        expected,
+    );
+}
+
+#[test]
+fn for_if_for_if_let_resume() {
+    let rows = &[
+        vec![Some(1), None],
+        vec![],
+        vec![None, Some(3)],
+        vec![Some(4)],
+    ];
+
+    let expected: Vec<(usize, i32)> = comp!(
+        (i, *value);
+        for (i, row) in rows.iter().enumerate();
+        if i != 3;
+        for cell in row;
+        if let Some(value) = cell;
+    )
+    .collect();
+
+    assert_resumes(
+        || {
+            comp_resumable!(
+                (i, *value);
+                for (i, row) in rows.iter().enumerate();
+                if i != 3;
+                for cell in row;
+                if let Some(value) = cell;
+            )
+        },
+        expected,
+    );
+}
+
+#[test]
+fn for_for_dependent_resume() {
+    let expected: Vec<(u32, u32)> = comp!((a, b); for a in 0..5; for b in 0..a).collect();
+
+    assert_resumes(
+        || comp_resumable!((a, b); for a in 0..5; for b in 0..a),
+        expected,
+    );
+}
+
+#[test]
+fn for_for_clone_iterator_resume() {
+    let source = "abc".chars().cycle().take(4);
+
+    assert_resumes(
+        || comp_resumable!(format!("{}{}", c, n); for c in source.clone(); for n in 0..2),
+        vec!["a0", "a1", "b0", "b1", "c0", "c1", "a0", "a1"]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+    );
+}
+
+#[test]
+fn cursor_indices() {
+    let mut items = comp_resumable!((a, b); for a in 0..3; for b in 0..4; if b % 2 == 1);
+
+    assert_eq!(items.cursor().indices(), &[0, 0]);
+    assert_eq!(items.nth(2), Some((1, 1)));
+    assert_eq!(items.cursor(), Cursor::new(vec![2, 2]));
+    items.by_ref().count();
+    assert_eq!(items.cursor().into_indices(), vec![3, 0]);
+}
+
+#[test]
+fn cursor_parse() {
+    assert_eq!("3, 1,4".parse::<Cursor>(), Ok(Cursor::from(vec![3, 1, 4])));
+    assert_eq!(Cursor::new(vec![3, 1, 4]).to_string(), "3,1,4");
+    assert!("3,x".parse::<Cursor>().is_err());
+}
+
+#[test]
+#[should_panic(expected = "cursor must have an index for every `for` clause")]
+fn resume_wrong_depth() {
+    let _ = comp_resumable!((a, b); for a in 0..3; for b in 0..4).resume(&Cursor::new(vec![1]));
+}
@@ -0,0 +1,75 @@
+//! Test the re-iterable comprehensions built by `comp_def!`.
+
+use py_comp::comp_def;
+
+#[test]
+fn for_for_iterated_twice() {
+    let x = &[1, 2, 3];
+    let y = &[10, 20];
+
+    let sums = comp_def!(a + b; for a in x; for b in y; if a % 2 == 1);
+
+    let first: Vec<i32> = (&sums).into_iter().collect();
+    let second: Vec<i32> = sums.iter().collect();
+
+    assert_eq!(first, vec![11, 21, 13, 23]);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn for_take_scan_restarts() {
+    let totals = comp_def!(total; for x in 1..; scan total = 0 => total + x; take 3);
+
+    for _ in 0..2 {
+        assert_eq!(totals.iter().collect::<Vec<i32>>(), vec![1, 3, 6]);
+    }
+}
+
+#[test]
+fn for_for_move_locals() {
+    let table = vec![String::from("ab"), String::from("cde")];
+    let repeats = vec![1, 2];
+
+    let rows = comp_def!(
+        move table, repeats;
+        name.repeat(n);
+        for name in table;
+        for n in repeats;
+        if name.len() * n < 5;
+    );
+
+    let mut scans = 0;
+    for _ in 0..3 {
+        let items: Vec<String> = rows.iter().collect();
+        assert_eq!(items, vec!["ab", "abab", "cde"]);
+        scans += 1;
+    }
+    assert_eq!(scans, 3);
+}
+
+#[test]
+fn for_loop_over_reference() {
+    let words = vec!["a", "bb", "ccc"];
+
+    let lengths = comp_def!(move words; word.len(); for word in words);
+
+    let mut total = 0;
+    for length in &lengths {
+        total += length;
+    }
+    for length in &lengths {
+        total += length;
+    }
+    assert_eq!(total, 12);
+}
+
+#[test]
+fn def_clone() {
+    let xs = vec![0, 1, 2, 3];
+
+    let squares = comp_def!(move xs; x * x; for x in xs);
+
+    let copy = squares.clone();
+
+    assert_eq!(copy.iter().sum::<i32>(), squares.iter().sum::<i32>());
+}
@@ -0,0 +1,131 @@
+//! Test the plans described by `comp_explain!`.
+
+use py_comp::comp_explain;
+use py_comp::explain::{Clause, ClauseKind};
+
+#[test]
+fn for_plan() {
+    let plan = comp_explain!(x * 2; for x in 0..3);
+
+    assert_eq!(plan.item, "x * 2");
+    assert_eq!(plan.levels.len(), 1);
+    assert_eq!(plan.levels[0].source, "for x in 0..3");
+    assert_eq!(plan.levels[0].rule, "for in");
+    assert_eq!(plan.levels[0].pipeline, "into_iter().map(..)");
+    assert_eq!(plan.levels[0].closure, "move |x| -> the item");
+    assert!(plan.levels[0].clauses.is_empty());
+}
+
+#[test]
+fn for_for_plan() {
+    let plan = comp_explain!((x, y); for x in 0..3; for y in 0..x);
+
+    let rules: Vec<_> = plan.levels.iter().map(|level| level.rule).collect();
+    assert_eq!(rules, vec!["for in for ...", "for in"]);
+    assert!(plan.levels[0].pipeline.starts_with("Product"));
+}
+
+#[test]
+fn for_if_if_let_for_plan() {
+    let plan = comp_explain!(
+        (x, y);
+        for x in 0..3;
+        if x > 0;
+        if let Some(y) = Some(x);
+        for z in 0..y;
+    );
+
+    assert_eq!(
+        plan.levels[0].rule,
+        "for in $( if | if-let | skip | step | scan )+ for ..."
+    );
+    assert_eq!(
+        plan.levels[0].pipeline,
+        "into_iter().filter_map(..).flatten()"
+    );
+    assert_eq!(
+        plan.levels[0].clauses,
+        vec![
+            Clause {
+                kind: ClauseKind::If,
+                source: "if x > 0"
+            },
+            Clause {
+                kind: ClauseKind::IfLet,
+                source: "if let Some(y) = Some(x)"
+            },
+        ]
+    );
+    assert_eq!(plan.levels[1].source, "for z in 0..y");
+}
+
+#[test]
+fn for_stateful_clauses_plan() {
+    let plan = comp_explain!(
+        total;
+        for x in 0..10;
+        skip 1;
+        step 2;
+        scan total = 0 => total + x;
+        take 3;
+    );
+
+    let clauses: Vec<_> = plan.levels[0]
+        .clauses
+        .iter()
+        .map(|clause| (clause.kind, clause.source))
+        .collect();
+    assert_eq!(
+        clauses,
+        vec![
+            (ClauseKind::Skip, "skip 1"),
+            (ClauseKind::Step, "step 2"),
+            (ClauseKind::Scan, "scan total = 0 => total + x"),
+            (ClauseKind::Take, "take 3"),
+        ]
+    );
+    assert_eq!(
+        plan.levels[0].rule,
+        "for in $( if | if-let | take | skip | step | scan )+"
+    );
+    assert_eq!(plan.levels[0].pipeline, "until(into_iter(), ..).flatten()");
+}
+
+#[test]
+fn plan_is_not_evaluated() {
+    let plan = comp_explain!(x; for x in panic!("evaluated"); if panic!("evaluated"));
+
+    assert_eq!(plan.levels[0].clauses.len(), 1);
+}
+
+#[test]
+fn plan_display() {
+    let plan = comp_explain!((x, y); for x in 0..3; if x > 0; for y in 0..x);
+
+    let expected = "\
+for x in 0..3
+    rule: for in $( if | if-let | skip | step | scan )+ for ...
+    pipeline: into_iter().filter_map(..).flatten()
+    closure: move |x| -> Some(the next level) if it passes the clauses, or None
+    if x > 0
+    for y in 0..x
+        rule: for in
+        pipeline: into_iter().map(..)
+        closure: move |y| -> the item
+        yield (x, y)
+";
+    assert_eq!(plan.to_string(), expected);
+}
+
+#[test]
+fn plan_to_dot() {
+    let plan = comp_explain!(s; for s in ["a\"b"].iter(); if s.len() > 1);
+
+    let dot = plan.to_dot();
+
+    assert!(dot.starts_with("digraph comp {"));
+    assert!(dot.contains("level0 -> level0_clause0;"));
+    assert!(dot.contains("level0_clause0 -> item;"));
+    assert!(dot.contains(r#"for s in [\"a\\\"b\"].iter()"#));
+    assert!(dot.trim_end().ends_with('}'));
+}
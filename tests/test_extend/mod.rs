@@ -0,0 +1,129 @@
+//! Test the `comp_extend!` macro.
+
+use py_comp::{comp, comp_extend, extend::Reserve};
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+
+/// A collection recording how it was reserved and extended.
+#[derive(Debug, Default)]
+struct Recorder {
+    reserved: Vec<usize>,
+    items: Vec<i32>,
+}
+
+impl Reserve for Recorder {
+    fn reserve(&mut self, additional: usize) {
+        self.reserved.push(additional);
+    }
+}
+
+impl Extend<i32> for Recorder {
+    fn extend<T: IntoIterator<Item = i32>>(&mut self, iter: T) {
+        self.items.extend(iter);
+    }
+}
+
+#[test]
+fn for_extend_vec() {
+    let mut items = vec![-1];
+
+    comp_extend!(items; x * 2; for x in 0..3);
+
+    assert_eq!(items, vec![-1, 0, 2, 4]);
+}
+
+#[test]
+fn for_if_for_extend_matches_comp() {
+    let xs = &[1, 2, 3, 4];
+    let ys = &[10, 20];
+    let mut items = Vec::new();
+
+    comp_extend!(items; a * b; for a in xs; if a % 2 == 0; for b in ys);
+
+    let expected: Vec<i32> = comp!(a * b; for a in xs; if a % 2 == 0; for b in ys).collect();
+    assert_eq!(items, expected);
+}
+
+#[test]
+fn for_extend_map_and_set() {
+    let mut map = HashMap::new();
+    map.insert(0, 'z');
+    let mut set = BTreeSet::new();
+
+    comp_extend!(map; (i, c); for (i, c) in "abc".chars().enumerate(); if i > 0);
+    comp_extend!(set; x % 3; for x in 0..10);
+
+    assert_eq!(map.len(), 3);
+    assert_eq!((map[&0], map[&2]), ('z', 'c'));
+    assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+}
+
+#[test]
+fn for_extend_field() {
+    struct Wrapper {
+        text: String,
+    }
+
+    let mut wrapper = Wrapper {
+        text: String::from(">"),
+    };
+
+    comp_extend!(wrapper.text; c; for c in "abc".chars(); if c != 'b');
+
+    assert_eq!(wrapper.text, ">ac");
+}
+
+#[test]
+fn for_extend_reserves_exact_size() {
+    let mut recorder = Recorder::default();
+
+    comp_extend!(recorder; x; for x in vec![1, 2, 3, 4]);
+
+    assert_eq!(recorder.reserved, vec![4]);
+    assert_eq!(recorder.items, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn for_for_extend_reserves_fixed_named_source() {
+    let xs = &[1, 2, 3];
+    let ys = &[10, 20];
+    let mut marked = Recorder::default();
+    let mut unmarked = Recorder::default();
+
+    comp_extend!(marked; a * b; for a in xs; for b in fixed ys);
+    comp_extend!(unmarked; a * b; for a in xs; for b in ys);
+
+    assert_eq!(marked.reserved, vec![6]);
+    assert_eq!(unmarked.reserved, vec![0]);
+    assert_eq!(marked.items, unmarked.items);
+}
+
+#[test]
+fn for_if_extend_reserves_lower_bound() {
+    let mut recorder = Recorder::default();
+
+    comp_extend!(recorder; x; for x in 0..10; if x % 2 == 0);
+
+    assert_eq!(recorder.reserved, vec![0]);
+    assert_eq!(recorder.items, vec![0, 2, 4, 6, 8]);
+}
+
+#[test]
+fn for_for_extend_builds_sources_once() {
+    let built = &Cell::new(0);
+    let mut recorder = Recorder::default();
+
+    comp_extend!(
+        recorder;
+        a * 2 + b;
+        for a in 0..3;
+        for b in {
+            built.set(built.get() + 1);
+            0..2
+        };
+    );
+
+    assert_eq!(built.get(), 3);
+    assert_eq!(recorder.reserved, vec![0]);
+    assert_eq!(recorder.items, vec![0, 1, 2, 3, 4, 5]);
+}
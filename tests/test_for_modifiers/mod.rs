@@ -0,0 +1,7 @@
+//! This module contains tests for the modifiers which may precede the
+//! expression following the `in` token of a `for` clause, such as `window`.
+//! The modifiers must behave the same in the first `for` clause and in any
+//! nested one, and must accept any `IntoIterator`, not only slices.
+
+mod test_chunks;
+mod test_windows;
@@ -0,0 +1,143 @@
+//! Test the `chunks` modifier and its remainder policies.
+
+use py_comp::comp;
+
+/// This is a stand-in for any type that does not implement Copy or Clone.
+#[derive(Debug, PartialEq, Eq)]
+struct Row(i32);
+
+#[test]
+fn for_in_chunks() {
+    let items: Vec<Vec<i32>> = comp!(batch; for batch in chunks 2 of 0..5).collect();
+
+    assert_eq!(items, vec![vec![0, 1], vec![2, 3], vec![4]]);
+}
+
+#[test]
+fn for_in_chunks_keep() {
+    let items: Vec<Vec<i32>> = comp!(batch; for batch in chunks 2 keep of 0..5).collect();
+
+    assert_eq!(items, vec![vec![0, 1], vec![2, 3], vec![4]]);
+}
+
+#[test]
+fn for_in_chunks_drop() {
+    let items: Vec<Vec<i32>> = comp!(batch; for batch in chunks 2 drop of 0..5).collect();
+
+    assert_eq!(items, vec![vec![0, 1], vec![2, 3]]);
+}
+
+#[test]
+fn for_in_chunks_pad() {
+    let items: Vec<Vec<i32>> = comp!(batch; for batch in chunks 3 pad (-1) of 0..5).collect();
+
+    assert_eq!(items, vec![vec![0, 1, 2], vec![3, 4, -1]]);
+}
+
+#[test]
+fn for_in_chunks_exact() {
+    let items: Vec<Vec<i32>> = comp!(batch; for batch in chunks 2 drop of 0..4).collect();
+
+    assert_eq!(items, vec![vec![0, 1], vec![2, 3]]);
+}
+
+#[test]
+fn for_in_chunks_of_uncloneable() {
+    let rows = (0..3).map(Row);
+
+    let items: Vec<Vec<Row>> = comp!(batch; for batch in chunks 2 of rows).collect();
+
+    assert_eq!(items, vec![vec![Row(0), Row(1)], vec![Row(2)]]);
+}
+
+#[test]
+fn for_in_chunks_size_from_variable() {
+    let batch_size = 4;
+
+    let items: Vec<usize> = comp!(batch.len(); for batch in chunks batch_size of 0..10).collect();
+
+    assert_eq!(items, vec![4, 4, 2]);
+}
+
+#[test]
+#[should_panic(expected = "chunk size must not be zero")]
+fn for_in_chunks_of_zero() {
+    let _ = comp!(batch; for batch in chunks 0 of 0..5);
+}
+
+#[test]
+fn for_in_array_chunks_drop() {
+    let items: Vec<i32> = comp!(a * b; for [a, b] in chunks [2] drop of 1..6).collect();
+
+    assert_eq!(items, vec![2, 12]);
+}
+
+#[test]
+fn for_in_array_chunks_pad() {
+    let items: Vec<[i32; 3]> = comp!(batch; for batch in chunks [3] pad 0 of 1..6).collect();
+
+    assert_eq!(items, vec![[1, 2, 3], [4, 5, 0]]);
+}
+
+#[test]
+fn for_in_array_chunks_of_uncloneable() {
+    let rows = (0..5).map(Row);
+
+    let items: Vec<[Row; 2]> = comp!(batch; for batch in chunks [2] drop of rows).collect();
+
+    assert_eq!(items, vec![[Row(0), Row(1)], [Row(2), Row(3)]]);
+}
+
+#[test]
+fn for_in_chunks_size_hint() {
+    let batches = comp!(batch; for batch in chunks 3 of 0..10);
+
+    assert_eq!(batches.size_hint(), (3, Some(4)));
+    assert_eq!(batches.count(), 4);
+}
+
+#[test]
+fn for_in_chunks_if() {
+    let items: Vec<i32> = comp!(
+        batch.iter().sum();
+        for batch in chunks 3 of 0..10;
+        if batch.len() == 3;
+    )
+    .collect();
+
+    assert_eq!(items, vec![3, 12, 21]);
+}
+
+#[test]
+fn for_in_chunks_for() {
+    let items: Vec<i32> = comp!(x; for batch in chunks 2 of 0..5; for x in batch).collect();
+
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn for_for_in_chunks() {
+    let rows = &[vec![1, 2, 3], vec![4, 5, 6, 7]];
+
+    let items: Vec<Vec<i32>> = comp!(
+        batch.into_iter().copied().collect();
+        for row in rows;
+        for batch in chunks 2 of row;
+    )
+    .collect();
+
+    assert_eq!(items, vec![vec![1, 2], vec![3], vec![4, 5], vec![6, 7]]);
+}
+
+/// Like `various_forms_of_usage` in the root module, this covers the parse
+/// paths of the modifier rather than its results.
+#[test]
+fn various_forms_of_usage() {
+    let x = &[Row(1), Row(2)];
+    let y = &[[Row(1), Row(2)], [Row(3), Row(4)]];
+
+    let _ = comp!(a; for a in chunks 2 of x);
+    let _ = comp!(a; for a in chunks 2 drop of x;);
+    let _ = comp!(a; for x in y; for [a, _] in chunks [2] drop of x);
+    let _ = comp!(a; for a in chunks [2] pad (&Row(0)) of x; if a[0] == &Row(123));
+}
@@ -0,0 +1,132 @@
+//! Test the `window` and `pairwise` modifiers.
+
+use py_comp::comp;
+
+use crate::Foo;
+
+#[test]
+fn for_in_window() {
+    let items: Vec<i32> = comp!(a + b + c; for [a, b, c] in window 3 of 0..6).collect();
+
+    assert_eq!(items, vec![3, 6, 9, 12]);
+}
+
+#[test]
+fn for_in_window_of_iterator() {
+    let text = "the quick brown fox";
+
+    let items: Vec<String> = comp!(
+        format!("{}-{}", a, b);
+        for [a, b] in window 2 of text.split(' ')
+    )
+    .collect();
+
+    assert_eq!(items, vec!["the-quick", "quick-brown", "brown-fox"]);
+}
+
+#[test]
+fn for_in_window_too_short() {
+    let items: Vec<[i32; 3]> = comp!(w; for w in window 3 of 0..2).collect();
+
+    assert_eq!(items, Vec::<[i32; 3]>::new());
+}
+
+#[test]
+fn for_in_window_of_one() {
+    let items: Vec<i32> = comp!(a; for [a] in window 1 of 0..3).collect();
+
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "window size must not be zero")]
+fn for_in_window_of_zero() {
+    let _ = comp!(w; for w in window 0 of 0..3);
+}
+
+#[test]
+fn for_in_window_if() {
+    let samples = &[1, 5, 2, 8, 3, 9, 4];
+
+    // Local maxima.
+    let items: Vec<i32> = comp!(
+        *b;
+        for [a, b, c] in window 3 of samples;
+        if a < b && b > c;
+    )
+    .collect();
+
+    assert_eq!(items, vec![5, 8, 9]);
+}
+
+#[test]
+fn for_in_window_size_hint() {
+    let windows = comp!(w; for w in window 3 of 0..10);
+
+    assert_eq!(windows.size_hint(), (8, Some(8)));
+    assert_eq!(windows.count(), 8);
+}
+
+#[test]
+fn for_in_pairwise() {
+    let items: Vec<i32> =
+        comp!(cur - prev; for (prev, cur) in pairwise vec![1, 4, 9, 16]).collect();
+
+    assert_eq!(items, vec![3, 5, 7]);
+}
+
+#[test]
+fn for_in_pairwise_too_short() {
+    let items: Vec<(i32, i32)> = comp!(pair; for pair in pairwise 0..1).collect();
+
+    assert_eq!(items, vec![]);
+}
+
+#[test]
+fn for_in_pairwise_infinite() {
+    let items: Vec<(u32, u32)> = comp!(pair; for pair in pairwise 0..; take 3).collect();
+
+    assert_eq!(items, vec![(0, 1), (1, 2), (2, 3)]);
+}
+
+#[test]
+fn for_for_in_window() {
+    let series = &[vec![1, 2, 3, 4], vec![10, 20], vec![5, 6, 7]];
+
+    let items: Vec<(usize, i32)> = comp!(
+        (i, a + b);
+        for (i, samples) in series.iter().enumerate();
+        for [a, b] in window 2 of samples;
+    )
+    .collect();
+
+    assert_eq!(
+        items,
+        vec![(0, 3), (0, 5), (0, 7), (1, 30), (2, 11), (2, 13)]
+    );
+}
+
+#[test]
+fn for_in_pairwise_for() {
+    let items: Vec<i32> = comp!(
+        x;
+        for (start, end) in pairwise &[0, 2, 5];
+        for x in *start..*end;
+    )
+    .collect();
+
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+}
+
+/// Like `various_forms_of_usage` in the root module, this covers the parse
+/// paths of the modifiers rather than their results.
+#[test]
+fn various_forms_of_usage() {
+    let x = &[Foo(1), Foo(2)];
+    let y = &[[Foo(1), Foo(2)], [Foo(3), Foo(4)]];
+
+    let _ = comp!(a; for [a, _] in window 2 of x);
+    let _ = comp!(a; for (a, _) in pairwise x;);
+    let _ = comp!(a; for [x, _] in window 2 of y; for a in x);
+    let _ = comp!(a; for x in y; for (a, _) in pairwise x; if *a == Foo(123));
+}
@@ -0,0 +1,123 @@
+//! Test the `comp_indexed!` macro and the `Indexed` iterator it expands to.
+
+use py_comp::{comp, comp_indexed};
+
+#[test]
+fn for_for_for_matches_comp() {
+    let x = &[1, 2, 3];
+    let y = vec![4, 5];
+    let y_ref = &y;
+
+    let items: Vec<(i32, i32, u8)> =
+        comp_indexed!((*a, *b, c); for a in x; for b in &y; for c in 0..4u8).collect();
+    let expected: Vec<(i32, i32, u8)> =
+        comp!((*a, *b, c); for a in x; for b in y_ref; for c in 0..4u8).collect();
+
+    assert_eq!(items, expected);
+}
+
+#[test]
+fn for_get() {
+    let items = comp_indexed!(a * 10 + b; for a in 0..3u8; for b in 0..=4u8);
+
+    assert_eq!(items.len(), 15);
+    assert_eq!(items.get(0), Some(0));
+    assert_eq!(items.get(7), Some(12));
+    assert_eq!(items.get(14), Some(24));
+    assert_eq!(items.get(15), None);
+}
+
+#[test]
+fn for_nth_and_skip() {
+    let mut items = comp_indexed!((a, b); for a in 0..1000; for b in 0..1000);
+
+    assert_eq!(items.nth(123_456), Some((123, 456)));
+    assert_eq!(items.len(), 1_000_000 - 123_457);
+    assert_eq!(items.get(0), Some((123, 457)));
+
+    let mut skipped = comp_indexed!((a, b); for a in 0..1000; for b in 0..1000).skip(999_998);
+    assert_eq!(skipped.next(), Some((999, 998)));
+    assert_eq!(skipped.next(), Some((999, 999)));
+    assert_eq!(skipped.next(), None);
+}
+
+#[test]
+fn for_nth_past_end() {
+    let mut items = comp_indexed!(a; for a in 0..3);
+
+    assert_eq!(items.nth(5), None);
+    assert_eq!(items.len(), 0);
+    assert_eq!(items.next(), None);
+}
+
+#[test]
+fn for_double_ended() {
+    let items = comp_indexed!((a, b); for a in 0..2; for b in &['x', 'y']);
+
+    assert_eq!(
+        items.rev().collect::<Vec<_>>(),
+        vec![(1, &'y'), (1, &'x'), (0, &'y'), (0, &'x')]
+    );
+
+    let mut items = comp_indexed!(a; for a in 0..10);
+    assert_eq!(items.nth_back(2), Some(7));
+    assert_eq!(items.next(), Some(0));
+    assert_eq!(items.last(), Some(6));
+}
+
+#[test]
+fn for_split_at() {
+    let letters = &["a", "b", "c"];
+    let mut items = comp_indexed!(format!("{}{}", a, b); for a in letters; for b in 1..3);
+    items.next();
+
+    let (front, back) = items.split_at(2);
+
+    assert_eq!(front.len(), 2);
+    assert_eq!(front.collect::<Vec<_>>(), vec!["a2", "b1"]);
+    assert_eq!(back.get(0), Some(String::from("b2")));
+    assert_eq!(back.collect::<Vec<_>>(), vec!["b2", "c1", "c2"]);
+}
+
+#[test]
+#[should_panic(expected = "split index is out of bounds")]
+fn for_split_at_out_of_bounds() {
+    let _ = comp_indexed!(a; for a in 0..3).split_at(4);
+}
+
+#[test]
+fn for_empty_source() {
+    let empty: &[i32] = &[];
+
+    let mut items = comp_indexed!((a, b); for a in 0..3; for b in empty);
+
+    assert!(items.is_empty());
+    assert_eq!(items.get(0), None);
+    assert_eq!(items.next(), None);
+}
+
+#[test]
+fn for_signed_ranges() {
+    let items = comp_indexed!(a; for a in i8::MIN..=i8::MAX);
+
+    assert_eq!(items.len(), 256);
+    assert_eq!(items.get(0), Some(i8::MIN));
+    assert_eq!(items.get(128), Some(0));
+    assert_eq!(items.get(255), Some(i8::MAX));
+}
+
+#[test]
+fn for_patterns() {
+    let pairs = &[(1, 'a'), (2, 'b')];
+
+    let items: Vec<String> =
+        comp_indexed!(format!("{}{}{}", n, c, d); for (n, c) in pairs; for d in 0..2).collect();
+
+    assert_eq!(items, vec!["1a0", "1a1", "2b0", "2b1"]);
+}
+
+#[test]
+#[should_panic(expected = "comprehension has too many items to index")]
+fn for_too_many_items() {
+    let _ = comp_indexed!((a, b); for a in 0..usize::MAX; for b in 0..3);
+}
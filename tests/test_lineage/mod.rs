@@ -0,0 +1,91 @@
+//! Test the `with lineage` and `with full lineage` modes of `comp!`.
+
+use py_comp::comp;
+
+#[test]
+fn for_lineage() {
+    let items: Vec<(char, [usize; 1])> = comp!(with lineage c; for c in "abc".chars()).collect();
+
+    assert_eq!(items, vec![('a', [0]), ('b', [1]), ('c', [2])]);
+}
+
+#[test]
+fn for_for_if_lineage() {
+    let rows = &[vec![1, 2], vec![3], vec![4, 5, 6]];
+
+    let items: Vec<(i32, [usize; 2])> =
+        comp!(with lineage *x; for row in rows; for x in row; if x % 2 == 0).collect();
+
+    assert_eq!(items, vec![(2, [0, 1]), (4, [2, 0]), (6, [2, 2])]);
+    for (x, [row, column]) in items {
+        assert_eq!(rows[row][column], x);
+    }
+}
+
+#[test]
+fn for_if_for_if_full_lineage() {
+    let rows = &[vec![1, 2], vec![3], vec![4, 5, 6]];
+
+    let items: Vec<(i32, [usize; 4])> = comp!(
+        with full lineage *x;
+        for row in rows;
+        if row.len() > 1;
+        for x in row;
+        if x % 2 == 0;
+    )
+    .collect();
+
+    assert_eq!(
+        items,
+        vec![(2, [0, 0, 1, 0]), (4, [2, 1, 0, 0]), (6, [2, 1, 2, 1])]
+    );
+}
+
+#[test]
+fn for_if_let_take_full_lineage() {
+    let items = &[None, Some('a'), None, Some('b'), Some('c')];
+
+    let lineage: Vec<(char, [usize; 2])> = comp!(
+        with full lineage *c;
+        for item in items;
+        if let Some(c) = item;
+        take 2;
+    )
+    .collect();
+
+    assert_eq!(lineage, vec![('a', [1, 0]), ('b', [3, 1])]);
+}
+
+#[test]
+fn for_lineage_matches_comp() {
+    let x = &[1, 2, 3];
+    let y = &[4, 5];
+
+    let items: Vec<(i32, [usize; 2])> =
+        comp!(with lineage a * b; for a in x; if a % 2 == 1; for b in y).collect();
+    let expected: Vec<i32> = comp!(a * b; for a in x; if a % 2 == 1; for b in y).collect();
+
+    assert_eq!(
+        comp!(*item; for (item, _) in &items).collect::<Vec<_>>(),
+        expected
+    );
+    for (item, [i, j]) in items {
+        assert_eq!(x[i] * y[j], item);
+    }
+}
+
+#[test]
+fn for_modifier_lineage() {
+    let index = 10;
+
+    let items: Vec<_> = comp!(with lineage (index, w); for w in window 2 of 0..4).collect();
+
+    assert_eq!(
+        items,
+        vec![
+            ((10, [0, 1]), [0]),
+            ((10, [1, 2]), [1]),
+            ((10, [2, 3]), [2])
+        ]
+    );
+}
@@ -0,0 +1,7 @@
+//! This module contains tests for the macros which expand the clauses of a
+//! comprehension to nested `for` loops instead of an iterator. Given the same
+//! clauses, they must visit the same items as `comp!` does.
+
+mod test_find;
+mod test_for_each;
+mod test_partition;
@@ -0,0 +1,126 @@
+//! Test the `comp_find!` macro.
+
+use py_comp::{comp, comp_find};
+use std::cell::Cell;
+
+#[test]
+fn for_find() {
+    assert_eq!(comp_find!(x; for x in 5..10), Some(5));
+    assert_eq!(comp_find!(x; for x in 0..0), None::<i32>);
+}
+
+#[test]
+fn for_if_find() {
+    let found = comp_find!(x * 10; for x in 1..; if x % 7 == 0);
+
+    assert_eq!(found, Some(70));
+}
+
+#[test]
+fn for_if_let_find() {
+    let items = &[None, Some("a"), Some("b")];
+
+    assert_eq!(
+        comp_find!(*s; for item in items; if let Some(s) = item),
+        Some("a")
+    );
+}
+
+#[test]
+fn for_for_find() {
+    let grid = &[[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+
+    let found = comp_find!(
+        (row, col);
+        for (row, cells) in grid.iter().enumerate();
+        for (col, cell) in cells.iter().enumerate();
+        if *cell % 5 == 0;
+    );
+
+    assert_eq!(found, Some((1, 1)));
+}
+
+#[test]
+fn for_for_find_none() {
+    let grid = &[[1, 2], [3, 4]];
+
+    let found = comp_find!(cell; for row in grid; for cell in row; if *cell > 4);
+
+    assert_eq!(found, None);
+}
+
+#[test]
+fn find_stops_at_first_item() {
+    let visited = Cell::new(0);
+
+    let found = comp_find!(
+        (x, y);
+        for x in 0..;
+        for y in 0..10;
+        if {
+            visited.set(visited.get() + 1);
+            x * y == 6
+        };
+    );
+
+    assert_eq!(found, Some((1, 6)));
+    assert_eq!(visited.get(), 17);
+}
+
+#[test]
+fn find_borrows_environment() {
+    // Unlike with `comp!`, the sources of nested levels do not need to be
+    // `Copy`, since there are no closures capturing them.
+    let needles = vec![String::from("b"), String::from("c")];
+    let haystacks = vec![String::from("abc"), String::from("xyz")];
+
+    let found = comp_find!(
+        (haystack.as_str(), needle.as_str());
+        for haystack in &haystacks;
+        for needle in &needles;
+        if haystack.contains(needle.as_str());
+    );
+
+    assert_eq!(found, Some(("abc", "b")));
+}
+
+#[test]
+fn find_with_stateful_clauses() {
+    let found = comp_find!(
+        (x, total);
+        for x in 0..;
+        step 3;
+        scan total = 0 => total + x;
+        skip 2;
+        if total > 40;
+    );
+
+    assert_eq!(found, Some((15, 45)));
+}
+
+#[test]
+fn find_with_take() {
+    assert_eq!(comp_find!(x; for x in 0..; take 3; if x > 5), None);
+    assert_eq!(comp_find!(x; for x in 0..; if x > 5; take 3), Some(6));
+}
+
+#[test]
+fn find_with_for_modifiers() {
+    let found = comp_find!(
+        (a, b);
+        for (a, b) in pairwise &[1, 3, 4, 8];
+        if b - a == 1;
+    );
+
+    assert_eq!(found, Some((&3, &4)));
+}
+
+#[test]
+fn find_agrees_with_comp() {
+    let rows = &[[3, 1, 4], [1, 5, 9], [2, 6, 5]];
+
+    assert_eq!(
+        comp_find!((a, b); for a in rows; for b in a; take 2; if *b > 4),
+        comp!((a, b); for a in rows; for b in a; take 2; if *b > 4).next()
+    );
+}
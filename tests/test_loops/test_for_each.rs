@@ -0,0 +1,157 @@
+//! Test the `comp_for_each!` macro.
+
+use py_comp::{comp, comp_for_each};
+
+#[test]
+fn for_for_each() {
+    let mut items = Vec::new();
+
+    comp_for_each!(for x in 0..3 => { items.push(x); });
+
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[test]
+fn for_if_for_for_each() {
+    let xs = &[1, 2, 3, 4];
+    let ys = &[10, 20];
+    let mut items = Vec::new();
+
+    comp_for_each!(for a in xs; if a % 2 == 0; for b in ys => { items.push(a * b); });
+
+    assert_eq!(items, vec![20, 40, 40, 80]);
+}
+
+#[test]
+fn for_each_with_trailing_clause() {
+    let mut items = Vec::new();
+
+    comp_for_each!(for x in 0..10; if x % 3 == 0 => { items.push(x) });
+    comp_for_each!(for x in 0..10; if let 4..=5 = x => { items.push(x) });
+    comp_for_each!(for x in 0..; take 2 => { items.push(x) });
+    comp_for_each!(for x in 0..5; skip 3 => { items.push(x) });
+    comp_for_each!(for x in 0..5; step 4 => { items.push(x) });
+    comp_for_each!(for x in 1..4; scan total = 0 => total + x => { items.push(total) });
+    comp_for_each!(for x in 1..3; scan total = 0 => { total + x } => { items.push(total) });
+
+    assert_eq!(
+        items,
+        vec![0, 3, 6, 9, 4, 5, 0, 1, 3, 4, 0, 4, 1, 3, 6, 1, 3]
+    );
+}
+
+#[test]
+fn for_each_take_does_not_pull_another_item() {
+    let mut pulled = 0;
+    let mut items = Vec::new();
+
+    comp_for_each!(for x in (0..10).inspect(|_| pulled += 1); take 3 => { items.push(x) });
+
+    assert_eq!(items, vec![0, 1, 2]);
+    assert_eq!(pulled, 3);
+}
+
+#[test]
+fn for_each_with_for_modifiers() {
+    let mut items = Vec::new();
+
+    comp_for_each!(for [a, b] in window 2 of 0..4 => { items.push(a * b) });
+    comp_for_each!(for (a, b) in pairwise 0..3 => { items.push(a + b) });
+    comp_for_each!(for batch in chunks 2 of 0..3 => { items.push(batch.len()) });
+
+    assert_eq!(items, vec![0, 2, 6, 1, 3, 2, 1]);
+}
+
+#[test]
+fn for_each_borrows_mutably() {
+    let rows = [vec![1, 2], vec![3, 4]];
+    let mut totals = vec![0; 2];
+    let mut count = 0;
+
+    comp_for_each!(
+        for (i, row) in rows.iter().enumerate();
+        for cell in row =>
+    {
+        totals[i] += cell;
+        count += 1;
+    });
+
+    assert_eq!(totals, vec![3, 7]);
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn for_each_continue_and_break() {
+    let mut items = Vec::new();
+
+    comp_for_each!(for x in 0..3; for y in 0..10 => {
+        if y == x {
+            continue;
+        }
+        if y > 2 {
+            break;
+        }
+        items.push((x, y));
+    });
+
+    assert_eq!(items, vec![(0, 1), (0, 2), (1, 0), (1, 2), (2, 0), (2, 1)]);
+}
+
+#[test]
+fn for_each_labeled_break() {
+    let mut items = Vec::new();
+
+    'outer: for round in 0.. {
+        comp_for_each!(for x in 0..3; for y in 0..3 => {
+            if round == 1 && x == 1 {
+                break 'outer;
+            }
+            items.push((round, x, y));
+        });
+    }
+
+    assert_eq!(items.len(), 12);
+    assert_eq!(items.last(), Some(&(1, 0, 2)));
+}
+
+#[test]
+fn for_each_return() {
+    fn first_pair_summing_to(target: i32, xs: &[i32]) -> Option<(i32, i32)> {
+        comp_for_each!(for a in xs; for b in xs; if a + b == target => {
+            return Some((*a, *b));
+        });
+        None
+    }
+
+    assert_eq!(first_pair_summing_to(7, &[1, 3, 4, 6]), Some((1, 6)));
+    assert_eq!(first_pair_summing_to(20, &[1, 3, 4, 6]), None);
+}
+
+#[test]
+fn for_each_agrees_with_comp() {
+    let rows = &[[3, 1, 4], [1, 5, 9], [2, 6, 5]];
+    let mut items = Vec::new();
+
+    comp_for_each!(
+        for row in rows;
+        skip 1;
+        for cell in row;
+        if *cell > 2;
+        scan total = 0 => total + cell =>
+    {
+        items.push((*cell, total));
+    });
+
+    assert_eq!(
+        items,
+        comp!(
+            (*cell, total);
+            for row in rows;
+            skip 1;
+            for cell in row;
+            if *cell > 2;
+            scan total = 0 => total + cell
+        )
+        .collect::<Vec<_>>()
+    );
+}
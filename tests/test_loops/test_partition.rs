@@ -0,0 +1,110 @@
+//! Test the `comp_partition!` and `comp_unzip!` macros.
+
+use py_comp::{comp, comp_partition, comp_unzip};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[test]
+fn for_partition() {
+    let (even, odd): (Vec<i32>, Vec<i32>) = comp_partition!(x % 2 == 0; x; for x in 0..7);
+
+    assert_eq!(even, vec![0, 2, 4, 6]);
+    assert_eq!(odd, vec![1, 3, 5]);
+}
+
+#[test]
+fn for_partition_with_types() {
+    let (small, large) = comp_partition!(x < 3 => (Vec<_>, BTreeSet<_>); x * 10; for x in 0..5);
+
+    assert_eq!(small, vec![0, 10, 20]);
+    assert_eq!(large, [30, 40].iter().copied().collect());
+}
+
+#[test]
+fn for_if_for_partition_matches_comp() {
+    let xs = &[1, 2, 3, 4];
+    let ys = &[10, 20, 30];
+
+    let (low, high): (Vec<i32>, Vec<i32>) = comp_partition!(
+        a * b < 50;
+        a * b;
+        for a in xs;
+        if a % 2 == 0;
+        for b in ys;
+    );
+
+    let low_expected: Vec<i32> =
+        comp!(a * b; for a in xs; if a % 2 == 0; for b in ys; if a * b < 50).collect();
+    let high_expected: Vec<i32> =
+        comp!(a * b; for a in xs; if a % 2 == 0; for b in ys; if a * b >= 50).collect();
+
+    assert_eq!(low, low_expected);
+    assert_eq!(high, high_expected);
+}
+
+#[test]
+fn for_partition_non_copy_items() {
+    let words = vec![String::from("a"), String::from("bb"), String::from("ccc")];
+
+    let (short, long): (Vec<String>, Vec<String>) =
+        comp_partition!(word.len() < 2; word; for word in words);
+
+    assert_eq!(short, vec!["a"]);
+    assert_eq!(long, vec!["bb", "ccc"]);
+}
+
+#[test]
+fn for_partition_borrows_locals() {
+    let names = vec![String::from("alice"), String::from("bob")];
+    let banned = vec![String::from("bob")];
+
+    let (flagged, _): (Vec<&String>, Vec<&String>) = comp_partition!(
+        name == ban;
+        name;
+        for name in &names;
+        for ban in &banned;
+    );
+
+    assert_eq!(flagged, vec![&names[1]]);
+}
+
+#[test]
+fn for_unzip() {
+    let (xs, squares): (Vec<i32>, Vec<i32>) = comp_unzip!((x, x * x); for x in 0..4);
+
+    assert_eq!(xs, vec![0, 1, 2, 3]);
+    assert_eq!(squares, vec![0, 1, 4, 9]);
+}
+
+#[test]
+fn for_for_if_unzip_with_types() {
+    let (pairs, sums, products) = comp_unzip!(
+        ((a, b), (a, a + b), a * b) => (Vec<_>, BTreeMap<_, _>, BTreeSet<_>);
+        for a in 1..4;
+        for b in 1..4;
+        if a < b;
+    );
+
+    assert_eq!(pairs, vec![(1, 2), (1, 3), (2, 3)]);
+    assert_eq!(sums, [(1, 4), (2, 5)].iter().copied().collect());
+    assert_eq!(products, [2, 3, 6].iter().copied().collect());
+}
+
+#[test]
+fn for_unzip_many() {
+    let (a, b, c, d, e): (Vec<_>, Vec<_>, Vec<_>, Vec<_>, String) =
+        comp_unzip!((x, x + 1, x + 2, x + 3, 'x'); for x in 0..2);
+
+    assert_eq!(a, vec![0, 1]);
+    assert_eq!(b, vec![1, 2]);
+    assert_eq!(c, vec![2, 3]);
+    assert_eq!(d, vec![3, 4]);
+    assert_eq!(e, "xx");
+}
+
+#[test]
+fn for_unzip_empty() {
+    let (xs, ys): (Vec<i32>, Vec<i32>) = comp_unzip!((x, x); for x in 0..0);
+
+    assert!(xs.is_empty());
+    assert!(ys.is_empty());
+}
@@ -0,0 +1,130 @@
+//! Test the `comp_owned!` macro and the `Shared` sources it moves into the
+//! comprehension.
+
+use py_comp::comp_owned;
+use py_comp::shared::Shared;
+use std::collections::HashMap;
+use std::thread;
+
+fn products(n: u32) -> impl Iterator<Item = u32> + Send + 'static {
+    let factors: Vec<u32> = (1..=n).collect();
+
+    comp_owned!(move factors; a * b; for a in factors; for b in factors; if a <= b)
+}
+
+#[test]
+fn for_for_returned() {
+    let items: Vec<u32> = products(3).collect();
+
+    assert_eq!(items, vec![1, 2, 3, 4, 6, 9]);
+}
+
+#[test]
+fn for_for_sent_to_thread() {
+    let items = products(2);
+
+    let items: Vec<u32> = thread::spawn(move || items.collect()).join().unwrap();
+
+    assert_eq!(items, vec![1, 2, 4]);
+}
+
+fn lookups() -> impl Iterator<Item = (String, u32)> {
+    let keys = vec![String::from("b"), String::from("z"), String::from("a")];
+    let values: HashMap<String, Vec<u32>> = vec![
+        (String::from("a"), vec![1]),
+        (String::from("b"), vec![2, 3]),
+    ]
+    .into_iter()
+    .collect();
+
+    comp_owned!(
+        move values;
+        (key.clone(), value);
+        for key in keys;
+        if let Some(found) = values.get(&key).cloned();
+        for value in found;
+    )
+}
+
+#[test]
+fn for_if_let_for_map() {
+    let items: Vec<(String, u32)> = lookups().collect();
+
+    assert_eq!(
+        items,
+        vec![
+            (String::from("b"), 2),
+            (String::from("b"), 3),
+            (String::from("a"), 1)
+        ]
+    );
+}
+
+#[test]
+fn for_for_several_locals() {
+    let rows = vec![String::from("ab"), String::from("c")];
+    let marks = vec!['!', '?'];
+
+    let items: Vec<String> = comp_owned!(
+        move rows, marks;
+        format!("{}{}", c, mark);
+        for row in rows;
+        take 1;
+        for c in row.chars().collect::<Vec<_>>();
+        for mark in marks;
+    )
+    .collect();
+
+    assert_eq!(items, vec!["a!", "a?", "b!", "b?"]);
+}
+
+#[test]
+fn for_modifier_for_owned() {
+    let xs = vec![1, 2, 3];
+
+    let items: Vec<i32> =
+        comp_owned!(move xs; a * b + x; for [a, b] in window 2 of xs; for x in xs).collect();
+
+    assert_eq!(items, vec![3, 4, 5, 7, 8, 9]);
+}
+
+#[test]
+fn for_chunks_owned() {
+    let xs = vec![1, 2, 3, 4, 5];
+
+    let items: Vec<i32> = comp_owned!(
+        move xs;
+        chunk.iter().sum::<i32>() * xs.len() as i32;
+        for chunk in chunks 2 of xs;
+    )
+    .collect();
+
+    assert_eq!(items, vec![15, 35, 25]);
+}
+
+#[test]
+fn shared_sequence_iterator() {
+    let shared = Shared::new(vec![
+        String::from("a"),
+        String::from("b"),
+        String::from("c"),
+    ]);
+
+    let mut items = shared.clone().into_iter();
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items.next_back(), Some(String::from("c")));
+    assert_eq!(items.next(), Some(String::from("a")));
+    assert_eq!(items.len(), 1);
+    assert_eq!(items.nth(3), None);
+    assert_eq!(items.next(), None);
+    assert_eq!(shared.len(), 3);
+}
+
+#[test]
+fn shared_buffered_iterator() {
+    let shared = Shared::new(String::from("héllo"));
+
+    assert_eq!(shared.clone().into_iter().collect::<String>(), "héllo");
+    assert_eq!((&Shared::new(vec![1, 2])).into_iter().sum::<i32>(), 3);
+}
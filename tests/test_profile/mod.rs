@@ -0,0 +1,127 @@
+//! Test the `comp_profiled!` macro and the report it records into.
+
+use py_comp::{comp, comp_profiled};
+
+/// The label, received and emitted counts of every clause of a report.
+fn counts(report: &py_comp::profile::Report) -> Vec<(&'static str, usize, usize)> {
+    report
+        .clauses()
+        .into_iter()
+        .map(|clause| (clause.label, clause.received, clause.emitted))
+        .collect()
+}
+
+#[test]
+fn for_if_for_if_let_profiled() {
+    let rows = &[
+        vec![Some(1), None],
+        vec![],
+        vec![None, Some(3)],
+        vec![Some(4)],
+    ];
+
+    let (items, report) = comp_profiled!(
+        *value;
+        for row in rows;
+        if !row.is_empty();
+        for cell in row;
+        if let Some(value) = cell;
+    );
+
+    assert_eq!(
+        counts(&report),
+        vec![
+            ("for row in rows", 1, 0),
+            ("if !row.is_empty()", 0, 0),
+            ("for cell in row", 0, 0),
+            ("if let Some(value) = cell", 0, 0),
+        ]
+    );
+
+    assert_eq!(items.collect::<Vec<i32>>(), vec![1, 3, 4]);
+
+    assert_eq!(
+        counts(&report),
+        vec![
+            ("for row in rows", 1, 4),
+            ("if !row.is_empty()", 4, 3),
+            ("for cell in row", 3, 5),
+            ("if let Some(value) = cell", 5, 3),
+        ]
+    );
+}
+
+#[test]
+fn for_profiled_matches_comp() {
+    let x = &[1, 2, 3, 4];
+    let y = &[10, 20];
+
+    let (items, _) = comp_profiled!(a * b; for a in x; if a % 2 == 0; for b in y; if a * b > 20);
+    let expected = comp!(a * b; for a in x; if a % 2 == 0; for b in y; if a * b > 20);
+
+    assert_eq!(items.collect::<Vec<i32>>(), expected.collect::<Vec<i32>>());
+}
+
+#[test]
+fn for_profiled_partially_consumed() {
+    let (mut items, report) = comp_profiled!(x; for x in 0..100; if x % 2 == 1);
+
+    assert_eq!(items.nth(2), Some(5));
+
+    assert_eq!(
+        counts(&report),
+        vec![("for x in 0..100", 1, 6), ("if x % 2 == 1", 6, 3)]
+    );
+}
+
+#[test]
+fn for_modifiers_and_other_clauses_profiled() {
+    let (items, report) = comp_profiled!(
+        total;
+        for [a, b] in window 2 of 0..6;
+        scan total = 0 => total + a + b;
+        for c in chunks 2 drop of 0..5;
+        take 1;
+        if c.len() == 2;
+    );
+
+    assert_eq!(items.collect::<Vec<i32>>(), vec![1, 4, 9, 16, 25]);
+    assert_eq!(
+        counts(&report),
+        vec![
+            ("for [a, b] in window 2 of 0..6", 1, 5),
+            ("for c in chunks 2 drop of 0..5", 5, 5),
+            ("if c.len() == 2", 5, 5),
+        ]
+    );
+}
+
+#[test]
+fn report_time_and_table() {
+    let (items, report) = comp_profiled!(x; for x in 0..1000; if x % 7 == 0);
+    items.for_each(drop);
+
+    let clauses = report.clauses();
+    assert!(clauses
+        .iter()
+        .all(|clause| clause.time > std::time::Duration::ZERO));
+
+    let table = report.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("clause"));
+    assert!(lines[1].starts_with("for x in 0..1000"));
+    assert!(lines[1].contains("1000"));
+    assert!(lines[2].starts_with("if x % 7 == 0"));
+    assert!(lines[2].contains("143"));
+}
+
+#[test]
+fn for_profiled_names_report() {
+    let report = 3;
+
+    let (items, profile) = comp_profiled!(x + report; for x in 0..2; if x >= report - 3);
+
+    assert_eq!(items.collect::<Vec<i32>>(), vec![3, 4]);
+    assert_eq!(profile.clauses().len(), 2);
+}
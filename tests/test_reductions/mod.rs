@@ -0,0 +1,186 @@
+//! This module contains tests for the macros reducing a comprehension to
+//! a single value, such as `comp_sum!` and `comp_any!`.
+
+use py_comp::{comp_all, comp_any, comp_count, comp_max, comp_min, comp_product, comp_sum};
+use std::cell::Cell;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Person {
+    name: &'static str,
+    age: u32,
+}
+
+const PEOPLE: &[Person] = &[
+    Person {
+        name: "alice",
+        age: 31,
+    },
+    Person {
+        name: "bob",
+        age: 25,
+    },
+    Person {
+        name: "carol",
+        age: 31,
+    },
+    Person {
+        name: "dave",
+        age: 25,
+    },
+];
+
+#[test]
+fn sum() {
+    assert_eq!(comp_sum!(x; for x in 1..=4), 10);
+    assert_eq!(comp_sum!(p.age; for p in PEOPLE; if p.age > 30), 62);
+}
+
+#[test]
+fn sum_empty() {
+    assert_eq!(comp_sum!(x; for x in 0..0), 0);
+}
+
+#[test]
+fn sum_floats() {
+    let weights = &[0.5, 0.25, 0.25];
+
+    assert_eq!(comp_sum!(w * 2.0; for w in weights), 2.0);
+}
+
+#[test]
+fn sum_nested() {
+    let grid = &[[1, 2, 3], [4, 5, 6]];
+
+    assert_eq!(
+        comp_sum!(*cell; for row in grid; for cell in row; take 2),
+        12
+    );
+}
+
+#[test]
+fn product() {
+    assert_eq!(comp_product!(x; for x in 1..=5), 120);
+    assert_eq!(comp_product!(x; for x in 0..0), 1);
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(comp_min!(p.age; for p in PEOPLE), Some(25));
+    assert_eq!(comp_max!(p.age; for p in PEOPLE), Some(31));
+    assert_eq!(comp_min!(x; for x in 0..0), None::<i32>);
+    assert_eq!(comp_max!(x; for x in 0..0), None::<i32>);
+}
+
+#[test]
+fn min_and_max_by_key() {
+    assert_eq!(comp_min!(p.age => p.name; for p in PEOPLE), Some("bob"));
+    assert_eq!(comp_max!(p.age => p.name; for p in PEOPLE), Some("alice"));
+    assert_eq!(
+        comp_max!(p.name.len() => p; for p in PEOPLE; if p.age < 30),
+        Some(&PEOPLE[3])
+    );
+    assert_eq!(comp_min!(x => x; for x in 0..0), None::<i32>);
+}
+
+#[test]
+fn min_and_max_return_first_of_equal_items() {
+    struct ByAge(&'static Person);
+
+    impl PartialEq for ByAge {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.age == other.0.age
+        }
+    }
+
+    impl Eq for ByAge {}
+
+    impl PartialOrd for ByAge {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for ByAge {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.age.cmp(&other.0.age)
+        }
+    }
+
+    let min = comp_min!(ByAge(p); for p in PEOPLE);
+    let max = comp_max!(ByAge(p); for p in PEOPLE);
+
+    assert_eq!(min.map(|p| p.0.name), Some("bob"));
+    assert_eq!(max.map(|p| p.0.name), Some("alice"));
+}
+
+#[test]
+fn min_by_key_nested() {
+    let points: &[(i32, i32)] = &[(0, 3), (1, 1), (2, 2)];
+
+    let closest = comp_min!(
+        (x - a).abs() + (y - b).abs() => (*x, *y, a, b);
+        for (x, y) in points;
+        for a in 0..3;
+        for b in 0..3;
+        if (a, b) != (*x, *y);
+    );
+
+    assert_eq!(closest, Some((0, 3, 0, 2)));
+}
+
+#[test]
+fn count() {
+    assert_eq!(comp_count!(for p in PEOPLE; if p.age == 25), 2);
+    assert_eq!(comp_count!(for x in 0..3; for _y in 0..x), 3);
+}
+
+#[test]
+fn any_and_all() {
+    assert!(comp_any!(p.age > 30; for p in PEOPLE));
+    assert!(!comp_any!(p.age > 40; for p in PEOPLE));
+    assert!(comp_all!(p.age > 20; for p in PEOPLE));
+    assert!(!comp_all!(p.age > 30; for p in PEOPLE));
+}
+
+#[test]
+fn any_and_all_empty() {
+    assert!(!comp_any!(x > 0; for x in 0..0));
+    assert!(comp_all!(x > 0; for x in 0..0));
+}
+
+#[test]
+fn any_short_circuits_across_levels() {
+    // This needs to be a reference because of how the closures capture
+    // their environment
+    let visited = &Cell::new(0);
+    let rows = &[[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+
+    let found = comp_any!(
+        {
+            visited.set(visited.get() + 1);
+            *cell == 5
+        };
+        for row in rows;
+        for cell in row;
+    );
+
+    assert!(found);
+    assert_eq!(visited.get(), 5);
+}
+
+#[test]
+fn all_short_circuits_across_levels() {
+    let visited = &Cell::new(0);
+
+    let all_small = comp_all!(
+        {
+            visited.set(visited.get() + 1);
+            x * y < 10
+        };
+        for x in 1..;
+        for y in 1..=3;
+    );
+
+    assert!(!all_small);
+    assert_eq!(visited.get(), 12);
+}
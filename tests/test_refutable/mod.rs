@@ -0,0 +1,194 @@
+//! Test `for` clauses with refutable patterns, which skip the items they do
+//! not match, and `for!` clauses, which panic on them.
+
+use py_comp::{comp, comp_explain, comp_for_each, comp_profiled, comp_resumable};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Click { x: i32, y: i32 },
+    Key(char),
+    Quit,
+}
+
+#[test]
+fn for_some() {
+    let options = &[Some(1), None, Some(3)];
+
+    let items: Vec<i32> = comp!(*x; for Some(x) in options).collect();
+
+    assert_eq!(items, vec![1, 3]);
+}
+
+#[test]
+fn for_ok_path_if() {
+    let results: Vec<Result<u8, String>> = vec![Ok(1), Err("bad".into()), Ok(20), Ok(3)];
+
+    let items: Vec<u8> = comp!(v; for Result::Ok(v) in results; if v < 10).collect();
+
+    assert_eq!(items, vec![1, 3]);
+}
+
+#[test]
+fn for_struct_variant_for_tuple_variant() {
+    let events = &[
+        Event::Click { x: 1, y: 2 },
+        Event::Key('a'),
+        Event::Click { x: 3, y: 4 },
+        Event::Quit,
+    ];
+    let keys = &[Event::Key('k'), Event::Quit, Event::Key('q')];
+
+    let items: Vec<(i32, i32, char)> = comp!(
+        (*x, *y, *c);
+        for Event::Click { x, y } in events;
+        for Event::Key(c) in keys;
+    )
+    .collect();
+
+    assert_eq!(
+        items,
+        vec![(1, 2, 'k'), (1, 2, 'q'), (3, 4, 'k'), (3, 4, 'q')]
+    );
+}
+
+#[test]
+fn for_unit_variant_count() {
+    let events = [Event::Quit, Event::Key('x'), Event::Quit];
+
+    assert_eq!(
+        comp!((); for Event::Quit in events.iter().copied()).count(),
+        2
+    );
+}
+
+#[test]
+fn for_some_skip_take() {
+    let options = &[None, Some(1), None, Some(2), Some(3), Some(4)];
+
+    // The `skip` and `take` clauses only count the items which matched.
+    let items: Vec<i32> = comp!(*x; for Some(x) in options; skip 1; take 2).collect();
+
+    assert_eq!(items, vec![2, 3]);
+}
+
+#[test]
+fn for_expect() {
+    let options = vec![Some(1), Some(2)];
+
+    let items: Vec<i32> = comp!(x * 10; for! Some(x) in options).collect();
+
+    assert_eq!(items, vec![10, 20]);
+}
+
+#[test]
+#[should_panic(expected = "an item of a `for!` clause does not match the pattern `Some(x)`")]
+fn for_for_expect_panics() {
+    let options = &[Some(1), None];
+
+    let _: Vec<(i32, i32)> = comp!((a, *x); for a in 0..2; for! Some(x) in options).collect();
+}
+
+#[test]
+fn for_each_and_resumable() {
+    let options = &[None, Some('a'), Some('b')];
+
+    let mut seen = String::new();
+    comp_for_each!(for Some(c) in options => { seen.push(*c) });
+    assert_eq!(seen, "ab");
+
+    let mut letters = comp_resumable!(*c; for Some(c) in options);
+    assert_eq!(letters.next(), Some('a'));
+    assert_eq!(letters.next(), Some('b'));
+    assert_eq!(letters.next(), None);
+}
+
+#[test]
+fn for_tuple_of_option() {
+    let pairs = vec![(Some(1), 'a'), (None, 'b'), (Some(3), 'c')];
+
+    let items: Vec<(i32, char)> = comp!((a, b); for (Some(a), b) in pairs).collect();
+
+    assert_eq!(items, vec![(1, 'a'), (3, 'c')]);
+}
+
+#[test]
+fn for_reference_to_some() {
+    let options = &[Some(1), None, Some(3)];
+
+    let items: Vec<i32> = comp!(x; for &Some(x) in options).collect();
+
+    assert_eq!(items, vec![1, 3]);
+}
+
+#[test]
+fn for_none_and_literals() {
+    let options = [None, Some(2), None];
+    let digits = [1, 2, 1, 3];
+
+    assert_eq!(comp!((); for None in options.iter().copied()).count(), 2);
+    assert_eq!(comp!((); for &1 in &digits).count(), 2);
+    assert_eq!(
+        comp!(x; for x @ 2..=3 in digits.iter().copied()).sum::<i32>(),
+        5
+    );
+}
+
+#[test]
+fn for_refutable_lineage() {
+    let options = &[None, Some(1), None, Some(2)];
+
+    let items: Vec<(i32, [usize; 1])> = comp!(with lineage *x; for Some(x) in options).collect();
+
+    assert_eq!(items, vec![(1, [1]), (2, [3])]);
+}
+
+#[test]
+fn for_refutable_full_lineage() {
+    let rows = &[vec![None, Some(1)], vec![Some(2), None, Some(3)]];
+
+    let items: Vec<(i32, [usize; 3])> = comp!(
+        with full lineage *x;
+        for row in rows;
+        for Some(x) in row;
+        if x % 2 == 1;
+    )
+    .collect();
+
+    assert_eq!(items, vec![(1, [0, 1, 0]), (3, [1, 2, 0])]);
+}
+
+#[test]
+fn for_refutable_profiled() {
+    let options = &[None, Some(1), None, Some(2)];
+
+    let (items, report) = comp_profiled!(*x; for Some(x) in options);
+    assert_eq!(items.collect::<Vec<i32>>(), vec![1, 2]);
+
+    let clauses = report.clauses();
+    assert_eq!(clauses[0].label, "for Some(x) in options");
+    assert_eq!((clauses[0].received, clauses[0].emitted), (1, 2));
+}
+
+#[test]
+fn explain_refutable() {
+    let plan = comp_explain!(x; for Some(x) in vec![Some(1)]);
+
+    assert_eq!(plan.levels[0].source, "for Some(x) in vec![Some(1)]");
+    assert!(plan.levels[0].clauses.is_empty());
+    assert_eq!(plan.levels[0].pipeline, "into_iter().filter_map(..)");
+
+    let plan = comp_explain!(x; for! Some(x) in vec![Some(1)]; if x > 0);
+
+    assert_eq!(plan.levels[0].source, "for! Some(x) in vec![Some(1)]");
+    assert_eq!(plan.levels[0].clauses.len(), 1);
+    assert_eq!(plan.levels[0].clauses[0].source, "if x > 0");
+}
+
+#[test]
+fn for_irrefutable_tuple_struct() {
+    struct Meters(u32);
+
+    let lengths = vec![Meters(3), Meters(4)];
+
+    assert_eq!(comp!(m; for Meters(m) in lengths).sum::<u32>(), 7);
+}
@@ -0,0 +1,185 @@
+//! Test the `size_hint` of comprehensions, which is exact for comprehensions
+//! with only `for` clauses over exact-size sources, when the nested sources
+//! are `fixed` or written with literals only.
+
+use std::cell::Cell;
+
+use py_comp::comp;
+
+fn assert_exact_size<I: Iterator>(iter: &I, len: usize) {
+    assert_eq!(iter.size_hint(), (len, Some(len)));
+}
+
+#[test]
+fn for_for_for_for_exact_size() {
+    let x = &[1, 2, 3];
+    let y = &[4, 5];
+    let z = &[6, 7, 8, 9];
+
+    let items = comp!(
+        (a, b, c, d);
+        for a in x;
+        for b in fixed y;
+        for c in fixed z;
+        for d in 0..5;
+    );
+
+    assert_exact_size(&items, 3 * 2 * 4 * 5);
+    assert_eq!(items.count(), 120);
+}
+
+#[test]
+fn for_for_literal_exact_size() {
+    let x = &[1, 2, 3];
+
+    let items = comp!((a, b, c); for a in x; for b in &[4, 5]; for c in 0..4);
+
+    assert_exact_size(&items, 3 * 2 * 4);
+    assert_eq!(items.count(), 24);
+}
+
+#[test]
+fn for_for_named_source_without_fixed() {
+    let x = &[1, 2, 3];
+    let y = &[4, 5];
+
+    let mut items = comp!((a, b); for a in x; for b in y);
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(items.next(), Some((&1, &4)));
+    assert_eq!(items.size_hint(), (1, None));
+    assert_eq!(items.count(), 5);
+}
+
+#[test]
+fn for_for_exact_size_while_consumed() {
+    let mut items = comp!((a, b); for a in 0..3; for b in 0..4);
+
+    for remaining in (0..12).rev() {
+        assert!(items.next().is_some());
+        assert_exact_size(&items, remaining);
+    }
+    assert_eq!(items.next(), None);
+    assert_exact_size(&items, 0);
+}
+
+#[test]
+fn for_for_dependent_size_hint() {
+    let mut items = comp!((a, b); for a in 0..5; for b in 0..a);
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(items.next(), Some((1, 0)));
+    assert_eq!(items.next(), Some((2, 0)));
+    assert_eq!(items.size_hint(), (1, None));
+    assert_eq!(items.count(), 8);
+}
+
+#[test]
+fn for_for_for_dependent_size_hint() {
+    let rows = &[vec![vec![1, 2], vec![3]], vec![], vec![vec![4, 5, 6]]];
+
+    let items = comp!(*x; for row in rows; for cell in row; for x in cell);
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(items.collect::<Vec<i32>>(), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn for_for_size_hint_does_not_build_sources() {
+    let built = &Cell::new(0);
+
+    let items = comp!(
+        (a, b);
+        for a in 0..3;
+        for b in {
+            built.set(built.get() + 1);
+            0..2
+        };
+    );
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(built.get(), 0);
+    assert_eq!(items.count(), 6);
+    assert_eq!(built.get(), 3);
+}
+
+#[test]
+fn for_for_huge_bounded_size_hint() {
+    let items = comp!((a, b); for a in 0..usize::MAX; for b in a..a + 2);
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(
+        items.take(3).collect::<Vec<_>>(),
+        vec![(0, 0), (0, 1), (1, 1)]
+    );
+
+    let items = comp!((a, b); for a in 0..usize::MAX; for b in 0..2);
+
+    assert_eq!(items.size_hint(), (usize::MAX, None));
+    assert_eq!(
+        items.take(3).collect::<Vec<_>>(),
+        vec![(0, 0), (0, 1), (1, 0)]
+    );
+
+    let items = comp!((a, b); for a in 0..usize::MAX / 2; for b in 0..2);
+
+    assert_exact_size(&items, usize::MAX - 1);
+    assert_eq!(
+        items.take(3).collect::<Vec<_>>(),
+        vec![(0, 0), (0, 1), (1, 0)]
+    );
+}
+
+#[test]
+fn for_for_fixed_source_built_once_more() {
+    let built = &Cell::new(0);
+
+    let items = comp!(
+        (a, b);
+        for a in 0..3;
+        for b in fixed {
+            built.set(built.get() + 1);
+            0..2
+        };
+    );
+
+    assert_eq!(built.get(), 1);
+    assert_exact_size(&items, 6);
+    assert_eq!(items.count(), 6);
+    assert_eq!(built.get(), 4);
+}
+
+#[test]
+fn for_for_if_size_hint() {
+    let mut items = comp!((a, b); for a in 0..3; for b in 0..4; if a < b);
+
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(items.next(), Some((0, 1)));
+    assert_eq!(items.size_hint(), (0, None));
+    assert_eq!(items.count(), 5);
+}
+
+#[test]
+fn for_for_unbounded_size_hint() {
+    let items = comp!((a, b); for a in 0..; for b in 0..2);
+
+    assert_eq!(items.size_hint(), (usize::MAX, None));
+    assert_eq!(
+        items.take(3).collect::<Vec<_>>(),
+        vec![(0, 0), (0, 1), (1, 0)]
+    );
+}
+
+#[test]
+fn for_for_non_clone_source() {
+    let mut source = vec![String::from("a"), String::from("b")];
+
+    let items: Vec<String> = comp!(
+        format!("{}{}", a, b);
+        for a in source.drain(..);
+        for b in 0..2;
+    )
+    .collect();
+
+    assert_eq!(items, vec!["a0", "a1", "b0", "b1"]);
+}
@@ -0,0 +1,161 @@
+//! Test the named iterator types defined by `comp_struct!`.
+
+use py_comp::{comp, comp_struct};
+
+comp_struct! {
+    struct Squares(n: u32) -> u32 {
+        x * x;
+        for x in 0..n => core::ops::Range<u32>;
+    }
+}
+
+comp_struct! {
+    #[derive(Clone, Debug)]
+    pub(crate) struct Cells<'a>(grid: &'a [Vec<i32>], min: i32) -> (usize, i32) {
+        (row, *cell);
+        for (row, cells) in grid.iter().enumerate() => core::iter::Enumerate<core::slice::Iter<'a, Vec<i32>>>;
+        if !cells.is_empty();
+        for cell in cells => &'a Vec<i32>;
+        if *cell >= min;
+    }
+}
+
+comp_struct! {
+    struct Parsed<'a>(words: &'a [&'a str]) -> (&'a str, u32) {
+        (word, n);
+        for word in words => &'a [&'a str];
+        if let Ok(count) = word.len().to_string().parse::<u32>()
+            => Result<u32, core::num::ParseIntError>;
+        if count > 1;
+        for n in 0..count => core::ops::Range<u32>;
+    }
+}
+
+struct Report<'a> {
+    cells: Cells<'a>,
+}
+
+trait Source {
+    type Items: Iterator<Item = u32>;
+
+    fn items(&self) -> Self::Items;
+}
+
+struct Limit(u32);
+
+impl Source for Limit {
+    type Items = Squares;
+
+    fn items(&self) -> Squares {
+        Squares::new(self.0)
+    }
+}
+
+#[test]
+fn for_struct() {
+    assert_eq!(Squares::new(4).collect::<Vec<u32>>(), vec![0, 1, 4, 9]);
+    assert_eq!(Squares::new(0).next(), None);
+}
+
+#[test]
+fn for_if_for_if_struct_field() {
+    let grid = vec![vec![1, 5], vec![], vec![7, 2, 9]];
+
+    let report = Report {
+        cells: Cells::new(&grid, 3),
+    };
+
+    let expected: Vec<(usize, i32)> = comp!(
+        (row, *cell);
+        for (row, cells) in grid.iter().enumerate();
+        if !cells.is_empty();
+        for cell in cells;
+        if *cell >= 3;
+    )
+    .collect();
+    assert_eq!(report.cells.collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn for_if_let_if_for_rebinds_pattern() {
+    let words = ["a", "bcd", "ef"];
+
+    let items: Vec<(&str, u32)> = Parsed::new(&words).collect();
+
+    assert_eq!(
+        items,
+        vec![("bcd", 0), ("bcd", 1), ("bcd", 2), ("ef", 0), ("ef", 1)]
+    );
+}
+
+#[test]
+fn associated_type() {
+    assert_eq!(Limit(3).items().sum::<u32>(), 5);
+}
+
+#[test]
+fn clone_and_fused() {
+    let grid = vec![vec![4, 5], vec![6]];
+
+    let mut cells = Cells::new(&grid, 0);
+    assert_eq!(cells.next(), Some((0, 4)));
+
+    let fork = cells.clone();
+    assert_eq!(cells.by_ref().count(), 2);
+    assert_eq!(cells.next(), None);
+    assert_eq!(cells.next(), None);
+    assert_eq!(fork.collect::<Vec<_>>(), vec![(0, 5), (1, 6)]);
+    assert!(format!("{:?}", Cells::new(&grid, 0)).starts_with("Cells"));
+}
+
+comp_struct! {
+    struct Triples(n: u32) -> (u32, u32, u32) {
+        (a, b, c);
+        for c in 1..n => core::ops::Range<u32>;
+        for b in 1..c => core::ops::Range<u32>;
+        for a in 1..b => core::ops::Range<u32>;
+        if a * a + b * b == c * c;
+    }
+}
+
+#[test]
+fn for_for_for_if_dependent_sources() {
+    let expected: Vec<(u32, u32, u32)> = comp!(
+        (a, b, c);
+        for c in 1..30u32;
+        for b in 1..c;
+        for a in 1..b;
+        if a * a + b * b == c * c;
+    )
+    .collect();
+
+    assert_eq!(Triples::new(30).collect::<Vec<_>>(), expected);
+    assert_eq!(expected.len(), 10);
+}
+
+#[test]
+#[deny(unused_variables)]
+fn for_if_let_for_matches_once_per_item() {
+    use core::cell::Cell;
+
+    comp_struct! {
+        struct Taken<'a>(slots: &'a [Cell<Option<u32>>], calls: &'a Cell<u32>) -> u32 {
+            h + j;
+            for slot in slots => &'a [Cell<Option<u32>>];
+            if let Some(h) = {
+                calls.set(calls.get() + 1);
+                slot.take()
+            } => Option<u32>;
+            for j in 0..100 => core::ops::Range<u32>;
+        }
+    }
+
+    let slots = [Cell::new(Some(0)), Cell::new(None), Cell::new(Some(1000))];
+    let calls = Cell::new(0);
+
+    let items: Vec<u32> = Taken::new(&slots, &calls).collect();
+
+    assert_eq!(calls.get(), 3);
+    assert_eq!(items.len(), 200);
+    assert_eq!((items[0], items[199]), (0, 1099));
+}
@@ -0,0 +1,300 @@
+//! Test the spans and events which `comp!` emits with the `tracing` feature.
+
+use py_comp::comp;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+type Fields = Vec<(&'static str, String)>;
+
+#[derive(Default)]
+struct FieldVisitor(Fields);
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+/// A span or an event, with the fields of the innermost entered span when it
+/// was recorded.
+#[derive(Debug, Clone, PartialEq)]
+struct Recorded {
+    name: &'static str,
+    fields: Fields,
+    parent: Option<Fields>,
+}
+
+#[derive(Default)]
+struct State {
+    spans: Vec<Recorded>,
+    metadata: Vec<&'static Metadata<'static>>,
+    events: Vec<Recorded>,
+    stack: Vec<u64>,
+}
+
+impl State {
+    fn parent(&self) -> Option<Fields> {
+        let id = *self.stack.last()?;
+        Some(self.spans[id as usize - 1].fields.clone())
+    }
+}
+
+#[derive(Clone, Default)]
+struct Recorder {
+    state: Arc<Mutex<State>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut visitor = FieldVisitor::default();
+        span.record(&mut visitor);
+        let mut state = self.state.lock().unwrap();
+        let parent = state.parent();
+        state.spans.push(Recorded {
+            name: span.metadata().name(),
+            fields: visitor.0,
+            parent,
+        });
+        state.metadata.push(span.metadata());
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        let mut state = self.state.lock().unwrap();
+        state.spans[span.into_u64() as usize - 1]
+            .fields
+            .extend(visitor.0);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut state = self.state.lock().unwrap();
+        let parent = state.parent();
+        state.events.push(Recorded {
+            name: "event",
+            fields: visitor.0,
+            parent,
+        });
+    }
+
+    fn enter(&self, span: &Id) {
+        self.state.lock().unwrap().stack.push(span.into_u64());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.state.lock().unwrap().stack.pop();
+    }
+
+    fn current_span(&self) -> Current {
+        let state = self.state.lock().unwrap();
+        match state.stack.last() {
+            Some(&id) => Current::new(Id::from_u64(id), state.metadata[id as usize - 1]),
+            None => Current::none(),
+        }
+    }
+}
+
+/// Run `f` with a recording subscriber, and return what it recorded.
+fn record<T>(f: impl FnOnce() -> T) -> (T, Vec<Recorded>, Vec<Recorded>) {
+    let recorder = Recorder::default();
+    let result = tracing::subscriber::with_default(recorder.clone(), f);
+    let state = recorder.state.lock().unwrap();
+    (result, state.spans.clone(), state.events.clone())
+}
+
+fn field<'a>(fields: &'a Fields, name: &str) -> &'a str {
+    &fields.iter().find(|(field, _)| *field == name).unwrap().1
+}
+
+fn clause(span: &Recorded) -> &str {
+    field(&span.fields, "clause")
+}
+
+#[test]
+fn for_if_for_spans() {
+    let (items, spans, _) =
+        record(|| comp!((a, b); for a in 0..3; if a > 0; for b in 0..2).collect::<Vec<_>>());
+
+    assert_eq!(items, vec![(1, 0), (1, 1), (2, 0), (2, 1)]);
+
+    let levels: Vec<&Recorded> = spans.iter().filter(|span| span.name == "comp").collect();
+    assert_eq!(levels.len(), 3);
+    assert_eq!(clause(levels[0]), "for a in 0..3");
+    assert!(levels[1..]
+        .iter()
+        .all(|span| clause(span) == "for b in 0..2"));
+
+    let items: Vec<(&str, &str)> = spans
+        .iter()
+        .filter(|span| span.name == "comp_item")
+        .map(|span| (clause(span), field(&span.fields, "item")))
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            ("for a in 0..3", "0"),
+            ("for a in 0..3", "1"),
+            ("for b in 0..2", "0"),
+            ("for b in 0..2", "1"),
+            ("for a in 0..3", "2"),
+            ("for b in 0..2", "0"),
+            ("for b in 0..2", "1"),
+        ]
+    );
+
+    // The span of a nested level is created while handling the item of the
+    // enclosing level it belongs to.
+    assert_eq!(field(levels[1].parent.as_ref().unwrap(), "item"), "1");
+}
+
+#[test]
+fn for_if_if_let_rejection_events() {
+    let items = &[Some(1), None, Some(2), Some(3)];
+
+    let (found, _, events) = record(|| {
+        comp!(*x; for item in items; if let Some(x) = item; if x % 2 == 1).collect::<Vec<i32>>()
+    });
+
+    assert_eq!(found, vec![1, 3]);
+
+    let rejections: Vec<(&str, &str)> = events
+        .iter()
+        .map(|event| {
+            (
+                field(&event.fields, "clause"),
+                field(event.parent.as_ref().unwrap(), "item"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rejections,
+        vec![
+            ("if let Some(x) = item", "None"),
+            ("if x % 2 == 1", "Some(2)"),
+        ]
+    );
+}
+
+#[test]
+fn for_refutable_rejection_events() {
+    let options = &[Some(1), None, Some(2)];
+
+    let (found, spans, events) = record(|| comp!(*x; for Some(x) in options).collect::<Vec<i32>>());
+
+    assert_eq!(found, vec![1, 2]);
+    assert!(spans
+        .iter()
+        .all(|span| clause(span) == "for Some(x) in options"));
+    assert_eq!(events.len(), 1);
+    assert_eq!(field(&events[0].fields, "clause"), "for Some(x)");
+    assert_eq!(field(events[0].parent.as_ref().unwrap(), "item"), "None");
+}
+
+#[test]
+fn for_pattern_binding_fields() {
+    let pairs = &[(1, 'a'), (2, 'b')];
+
+    let (found, spans, _) = record(|| comp!(n; for &(n, ref c) in pairs).collect::<Vec<i32>>());
+
+    assert_eq!(found, vec![1, 2]);
+    let bindings: Vec<(&str, &str)> = spans
+        .iter()
+        .filter(|span| span.name == "comp_item")
+        .map(|span| (field(&span.fields, "n"), field(&span.fields, "c")))
+        .collect();
+    assert_eq!(bindings, vec![("1", "'a'"), ("2", "'b'")]);
+}
+
+#[test]
+fn for_refutable_pattern_binding_fields() {
+    enum Shape {
+        Circle { radius: i32 },
+        Square(i32),
+    }
+
+    let shapes = &[Shape::Circle { radius: 1 }, Shape::Square(2)];
+
+    let (found, spans, _) =
+        record(|| comp!(*radius; for Shape::Circle { radius } in shapes).collect::<Vec<i32>>());
+
+    assert_eq!(found, vec![1]);
+    let radii: Vec<Option<&str>> = spans
+        .iter()
+        .filter(|span| span.name == "comp_item")
+        .map(|span| {
+            span.fields
+                .iter()
+                .find(|(name, _)| *name == "radius")
+                .map(|(_, value)| value.as_str())
+        })
+        .collect();
+    assert_eq!(radii, vec![Some("1"), None]);
+    assert!(matches!(shapes[1], Shape::Square(2)));
+}
+
+#[test]
+fn rejection_event_binding_fields() {
+    let items = &[(1, Some(10)), (2, None), (3, Some(31))];
+
+    let (found, _, events) = record(|| {
+        comp!(y; for (x, item) in items; if let Some(y) = item; if y % 2 == 0).collect::<Vec<_>>()
+    });
+
+    assert_eq!(found, vec![&10]);
+
+    let names =
+        |event: &Recorded| -> Vec<&str> { event.fields.iter().map(|(name, _)| *name).collect() };
+    assert_eq!(events.len(), 2);
+    assert_eq!(names(&events[0]), vec!["message", "clause", "x", "item"]);
+    assert_eq!(field(&events[0].fields, "x"), "2");
+    assert_eq!(field(&events[0].fields, "item"), "None");
+    assert_eq!(
+        names(&events[1]),
+        vec!["message", "clause", "x", "item", "y"]
+    );
+    assert_eq!(field(&events[1].fields, "clause"), "if y % 2 == 0");
+    assert_eq!(field(&events[1].fields, "y"), "31");
+}
+
+#[test]
+fn for_non_debug_item() {
+    struct Opaque(i32);
+
+    let values = vec![Opaque(1), Opaque(2)];
+
+    let (sum, spans, _) = record(|| comp!(o.0; for o in &values).sum::<i32>());
+
+    assert_eq!(sum, 3);
+    let items: Vec<&str> = spans
+        .iter()
+        .filter(|span| span.name == "comp_item")
+        .map(|span| field(&span.fields, "item"))
+        .collect();
+    assert_eq!(items, vec!["_", "_"]);
+}
+
+#[test]
+fn for_for_exact_size_with_tracing() {
+    let items = comp!((a, b); for a in 0..3; for b in 0..2);
+
+    assert_eq!(items.size_hint(), (6, Some(6)));
+}